@@ -0,0 +1,203 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::{model, query};
+
+/// How far an export got, so that it can pick up where it left off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// How far each database being exported got.
+    pub databases: Vec<Progress>,
+    /// The length of each output file once those officers were written.
+    pub output_lengths: BTreeMap<PathBuf, u64>,
+    /// Which officers the export lists; a resumed export must list the same.
+    #[serde(default, skip_serializing_if = "query::IndexFilter::is_empty")]
+    pub index_filter: query::IndexFilter,
+    /// Which allegations the export fetches; a resumed export must fetch the same.
+    #[serde(default, skip_serializing_if = "query::DetailsFilter::is_empty")]
+    pub details_filter: query::DetailsFilter,
+    /// The officers written so far, when exporting more than one database.
+    #[serde(default, skip_serializing_if = "Overlap::is_empty")]
+    pub overlap: Overlap,
+}
+
+/// How far the export of one database got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Progress {
+    pub database: query::Database,
+    /// The restart tokens of the most recently fetched index page.
+    pub restart_tokens: Option<Vec<String>>,
+    /// The last officer whose details were completely written.
+    pub last_officer: Option<model::Officer>,
+}
+
+/// Which databases the officers written so far came from, to find the ids
+/// that turn up in more than one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Overlap {
+    /// The database each id was first found in.
+    pub seen: BTreeMap<String, query::Database>,
+    /// The ids found in more than one database.
+    pub in_both: BTreeSet<String>,
+}
+
+impl Overlap {
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Write the ids found in more than one database as CSV, with an `id`
+    /// header like the SQLite `officers_in_both` view.
+    pub fn write_in_both<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        let mut writer = csv::Writer::from_writer(writer);
+
+        writer.write_record(["id"])?;
+        for id in &self.in_both {
+            writer.write_record([id])?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Note that `officer` was written.
+    pub fn record(&mut self, officer: &model::Officer) {
+        if let Some(other) = self.seen.insert(officer.id.clone(), officer.status) {
+            if other != officer.status {
+                self.in_both.insert(officer.id.clone());
+            }
+        }
+    }
+}
+
+impl Checkpoint {
+    /// Read a checkpoint, or `None` if there isn't one at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>, Error> {
+        let path = path.as_ref();
+
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let checkpoint = serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|source| Error::Checkpoint { path: path.into(), source })?;
+
+        Ok(Some(checkpoint))
+    }
+
+    /// Write the checkpoint to `path`.
+    ///
+    /// The checkpoint is written to a temporary file first and moved into
+    /// place, so a crash mid-write leaves the previous checkpoint intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|source| Error::Checkpoint { path: path.into(), source })?;
+
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)?;
+
+        Ok(())
+    }
+
+    /// How far the given database got, if it was being exported.
+    pub fn progress(&self, database: query::Database) -> Option<&Progress> {
+        self.databases.iter().find(|progress| progress.database == database)
+    }
+
+    /// The recorded length of the output file at `path`.
+    pub fn output_length<P: AsRef<Path>>(&self, path: P) -> Option<u64> {
+        self.output_lengths.get(path.as_ref()).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("ccrb-export-checkpoint-{}.json", std::process::id()));
+
+        assert!(Checkpoint::load(&path).expect("load missing").is_none());
+
+        let mut output_lengths = BTreeMap::new();
+        output_lengths.insert(PathBuf::from("officers.csv"), 1234);
+
+        let checkpoint = Checkpoint {
+            databases: vec![Progress {
+                database: query::Database::Active,
+                restart_tokens: Some(vec!["'001 PD'".into(), "'12345'".into()]),
+                last_officer: Some(model::Officer {
+                    last_name: "O'Brien".into(),
+                    ..model::Officer::example()
+                }),
+            }],
+            output_lengths,
+            index_filter: query::IndexFilter {
+                ranks: vec!["Police Officer".into()],
+                ..query::IndexFilter::default()
+            },
+            details_filter: query::DetailsFilter::default(),
+            overlap: Overlap::default(),
+        };
+
+        checkpoint.save(&path).expect("save");
+        let loaded = Checkpoint::load(&path).expect("load").expect("checkpoint");
+        std::fs::remove_file(&path).expect("cleanup");
+
+        let progress = loaded.progress(query::Database::Active).expect("progress");
+
+        assert_eq!(progress.restart_tokens, checkpoint.databases[0].restart_tokens);
+        assert_eq!(progress.last_officer.as_ref().expect("officer").last_name, "O'Brien");
+        assert!(loaded.progress(query::Database::Inactive).is_none());
+        assert_eq!(loaded.output_length("officers.csv"), Some(1234));
+        assert_eq!(loaded.output_length("details.csv"), None);
+        assert_eq!(loaded.index_filter, checkpoint.index_filter);
+    }
+
+    #[test]
+    fn overlap_survives_resume() {
+        let path = std::env::temp_dir().join(format!("ccrb-export-overlap-{}.json", std::process::id()));
+
+        let officer = |id: &str, status| model::Officer {
+            id: id.into(),
+            status,
+            ..model::Officer::example()
+        };
+
+        let mut overlap = Overlap::default();
+        overlap.record(&officer("1", query::Database::Active));
+        overlap.record(&officer("2", query::Database::Active));
+
+        Checkpoint {
+            databases: vec![],
+            output_lengths: BTreeMap::new(),
+            index_filter: query::IndexFilter::default(),
+            details_filter: query::DetailsFilter::default(),
+            overlap,
+        }.save(&path).expect("save");
+        let resumed = Checkpoint::load(&path).expect("load").expect("checkpoint");
+        std::fs::remove_file(&path).expect("cleanup");
+
+        // Officers written before the checkpoint still count once resumed.
+        let mut overlap = resumed.overlap;
+        overlap.record(&officer("1", query::Database::Inactive));
+        overlap.record(&officer("3", query::Database::Inactive));
+
+        let mut csv = vec![];
+        overlap.write_in_both(&mut csv).expect("write");
+
+        assert_eq!(String::from_utf8(csv).expect("utf-8"), "id\n1\n");
+    }
+}
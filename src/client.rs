@@ -0,0 +1,501 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::error::{Error, QueryContext};
+use crate::{iter, model, query, report, response, retry, schema, HOST, USER_AGENT};
+
+/// How long a request may take, from connecting to reading the whole
+/// response, unless set with `Client::with_timeout`.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long connecting to the service may take.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A handle for querying the CCRB PowerBI reports.
+///
+/// Cloning a `Client` is cheap; clones share the same connection pool.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    host: String,
+    pub(crate) window: usize,
+    pub(crate) index_filter: query::IndexFilter,
+    pub(crate) details_filter: query::DetailsFilter,
+    rate_limit: Option<RateLimit>,
+    retry: retry::RetryPolicy,
+    timeout: Duration,
+    reports: report::Reports,
+}
+
+/// Spaces out requests made through any clone of a `Client`.
+#[derive(Debug, Clone)]
+struct RateLimit {
+    interval: Duration,
+    next: Arc<Mutex<Instant>>,
+}
+
+impl RateLimit {
+    fn new(interval: Duration) -> Self {
+        RateLimit {
+            interval,
+            next: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    async fn wait(&self) {
+        let mut next = self.next.lock().await;
+
+        tokio::time::sleep_until(*next).await;
+
+        *next = Instant::now() + self.interval;
+    }
+}
+
+impl Client {
+    /// Build a client with the headers the public report expects.
+    pub fn new() -> Result<Self, reqwest::Error> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("Accept", reqwest::header::HeaderValue::from_static("application/json, text/plain, */*"));
+        headers.insert("ActivityId", reqwest::header::HeaderValue::from_static("a366f021-d490-ed01-6681-0fe32cf1255a"));
+        headers.insert("RequestId", reqwest::header::HeaderValue::from_static("141bd242-9744-e182-52a8-ed8a1633b878"));
+        headers.insert("Content-Type", reqwest::header::HeaderValue::from_static("application/json;charset=UTF-8"));
+        headers.insert("Origin", reqwest::header::HeaderValue::from_static("https://app.powerbigov.us"));
+        headers.insert("Connection", reqwest::header::HeaderValue::from_static("keep-alive"));
+        headers.insert("Sec-GPC", reqwest::header::HeaderValue::from_static("1"));
+        headers.insert("Pragma", reqwest::header::HeaderValue::from_static("no-cache"));
+        headers.insert("Cache-Control", reqwest::header::HeaderValue::from_static("no-cache"));
+
+        let http = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .default_headers(headers)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()?;
+
+        Ok(Client::from_reqwest(http))
+    }
+
+    /// Wrap an existing `reqwest` client, e.g. one with custom proxy settings.
+    pub fn from_reqwest(http: reqwest::Client) -> Self {
+        Client {
+            http,
+            host: HOST.into(),
+            window: query::DEFAULT_WINDOW,
+            index_filter: query::IndexFilter::default(),
+            details_filter: query::DetailsFilter::default(),
+            rate_limit: None,
+            retry: retry::RetryPolicy::default(),
+            timeout: DEFAULT_TIMEOUT,
+            reports: report::Reports::default(),
+        }
+    }
+
+    /// Send requests to another PowerBI cluster than `HOST`.
+    pub fn with_host<S: Into<String>>(mut self, host: S) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// Set the number of officers fetched per page of the index.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Only list the officers matching `filter` from the index.  The
+    /// filter is applied by the service, on every page.
+    pub fn with_index_filter(mut self, filter: query::IndexFilter) -> Self {
+        self.index_filter = filter;
+        self
+    }
+
+    /// Only fetch the allegations matching `filter` for each officer.
+    pub fn with_details_filter(mut self, filter: query::DetailsFilter) -> Self {
+        self.details_filter = filter;
+        self
+    }
+
+    /// Wait at least `interval` between the start of consecutive requests.
+    ///
+    /// The limit is shared by every clone of this client, so it caps the
+    /// overall request rate however many queries are in flight.
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.rate_limit = Some(RateLimit::new(interval));
+        self
+    }
+
+    /// Set how failed queries are retried.
+    pub fn with_retry(mut self, retry: retry::RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Give up on a request, and maybe retry it, if it takes longer than
+    /// `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set where each database is published.
+    pub fn with_reports(mut self, reports: report::Reports) -> Self {
+        self.reports = reports;
+        self
+    }
+
+    /// Set where one database is published.
+    pub fn with_report(mut self, db: query::Database, report: report::Report) -> Self {
+        *self.reports.get_mut(db) = report;
+        self
+    }
+
+    /// Where `db` is published.
+    pub fn report(&self, db: query::Database) -> &report::Report {
+        self.reports.get(db)
+    }
+
+    /// Look up the model, dataset and report ids of `db` from its public
+    /// report's metadata, using the report's resource key.
+    pub async fn discover(&self, db: query::Database) -> Result<report::Report, Error> {
+        let context = QueryContext::metadata(db);
+        let report = self.report(db);
+
+        let url = format!("{}/public/reports/{}/modelsAndExploration?preferReadOnlySession=true", self.host, report.resource_key);
+
+        let metadata: response::ModelsAndExploration = self.retrying(|| self.try_fetch(&context, self.http.get(&url)))
+            .await?;
+
+        let missing = |missing| Error::Discovery { query: context.clone(), missing };
+        let exploration = metadata.report().ok_or_else(|| missing("report"))?;
+        let model = metadata.model().ok_or_else(|| missing("model"))?;
+
+        Ok(report::Report {
+            model_id: model.id,
+            dataset_id: model.db_name.clone(),
+            report_id: exploration.object_id.clone(),
+            ..report.clone()
+        })
+    }
+
+    /// Fetch the conceptual schema of `db`'s model: its entities, and their
+    /// columns and measures.
+    pub async fn schema(&self, db: query::Database) -> Result<Vec<schema::Entity>, Error> {
+        let context = QueryContext::schema(db);
+        let report = self.report(db);
+
+        let url = format!("{}/public/reports/conceptualschema", self.host);
+        let body = serde_json::json!({
+            "modelIds": [report.model_id],
+            "userPreferredLocale": "en-US",
+        });
+
+        let resp: response::ConceptualSchemaResponse = self.retrying(|| self.try_fetch(&context, self.http.post(&url).json(&body)))
+            .await?;
+
+        let model = resp.schemas
+            .iter()
+            .find(|schema| schema.model_id == Some(report.model_id))
+            .ok_or(Error::Discovery { query: context.clone(), missing: "schema" })?;
+
+        Ok(model.schema.entities.iter().map(schema::Entity::from).collect())
+    }
+
+    /// Count the allegations of `db` in each group of `group_by`, over the
+    /// officers and allegations the client's filters allow.
+    pub async fn summary(&self, db: query::Database, group_by: &[model::SummaryGroup]) -> Result<Vec<model::Summary>, Error> {
+        let mut summary = vec![];
+        let mut restart_tokens = None;
+
+        loop {
+            let context = QueryContext::summary(db, restart_tokens.clone());
+            let req = query::get_summary(self.report(db), group_by, &self.index_filter, &self.details_filter, restart_tokens, self.window);
+
+            let resp = self.query(&context, &req)
+                .await?;
+
+            let columns = resp.get_columns()
+                .map_err(|source| Error::Decode { query: context.clone(), source })?;
+            let rows = resp.get_data()
+                .map_err(|source| Error::Decode { query: context.clone(), source })?;
+
+            for row in rows {
+                let row = model::Summary::from_row(db, group_by, model::Row::new(&columns, row))
+                    .map_err(|source| Error::Row { query: context.clone(), source })?;
+                summary.push(row);
+            }
+
+            restart_tokens = resp.get_restart_tokens();
+            if restart_tokens.is_none() {
+                return Ok(summary);
+            }
+        }
+    }
+
+    /// Start paging through the officer index of `db`.
+    pub async fn index(&self, db: query::Database) -> Result<iter::Index, Error> {
+        iter::Index::new(self.clone(), db).await
+    }
+
+    /// Start paging through the officer index of `db`, after the given officer.
+    pub async fn index_after_officer(&self, db: query::Database, officer: &model::Officer) -> Result<iter::Index, Error> {
+        iter::Index::new_after_officer(self.clone(), db, officer).await
+    }
+
+    /// Fetch the allegation details for one officer.
+    pub async fn details(&self, db: query::Database, officer: &model::Officer) -> Result<iter::Details, Error> {
+        iter::Details::new(self, db, officer).await
+    }
+
+    pub(crate) async fn query(&self, context: &QueryContext, req: &query::Request) -> Result<response::Response, Error> {
+        self.retrying(|| self.try_query(context, req))
+            .await
+    }
+
+    /// Make a request, retrying it according to the retry policy.
+    async fn retrying<T, F, R>(&self, mut request: F) -> Result<T, Error>
+    where
+        F: FnMut() -> R,
+        R: std::future::Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match request().await {
+                Ok(resp) => return Ok(resp),
+                Err(e) if e.is_retryable() && attempt < self.retry.max_attempts => {
+                    let backoff = self.retry.jittered_backoff(attempt);
+                    log::warn!("attempt {} of {} failed, retrying in {:?}: {}", attempt, self.retry.max_attempts, backoff, e);
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn try_query(&self, context: &QueryContext, req: &query::Request) -> Result<response::Response, Error> {
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.wait().await;
+        }
+
+        let transport = |source| Error::Transport { query: context.clone(), source };
+
+        let report = self.report(context.database);
+
+        let resp = self.http.post(&format!("{}/public/reports/querydata?synchronous=true", self.host))
+            .header("X-PowerBI-ResourceKey", &report.resource_key)
+            .header("Referer", &report.referer)
+            .timeout(self.timeout)
+            .json(req)
+            .send()
+            .await
+            .map_err(transport)?;
+
+        let status = resp.status();
+
+        let body = resp.bytes()
+            .await
+            .map_err(transport)?;
+
+        let parsed = serde_json::from_slice::<response::Response>(&body);
+
+        if let Some(e) = parsed.as_ref().ok().and_then(|r| r.get_service_error()) {
+            return Err(Error::Service { query: context.clone(), source: Box::new(e.clone()) });
+        }
+
+        if !status.is_success() {
+            return Err(Error::HttpStatus { query: context.clone(), status });
+        }
+
+        parsed
+            .map_err(|source| Error::Json { query: context.clone(), source })
+    }
+
+    /// Send a request for the report's resources, other than a query.
+    async fn try_fetch<T: serde::de::DeserializeOwned>(&self, context: &QueryContext, req: reqwest::RequestBuilder) -> Result<T, Error> {
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.wait().await;
+        }
+
+        let transport = |source| Error::Transport { query: context.clone(), source };
+
+        let report = self.report(context.database);
+
+        let resp = req
+            .header("X-PowerBI-ResourceKey", &report.resource_key)
+            .header("Referer", &report.referer)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(transport)?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(Error::HttpStatus { query: context.clone(), status });
+        }
+
+        let body = resp.bytes()
+            .await
+            .map_err(transport)?;
+
+        serde_json::from_slice(&body)
+            .map_err(|source| Error::Json { query: context.clone(), source })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // A modelsAndExploration response for the active report, with the ids
+    // the report is known to use.  It is written by hand, not captured, so
+    // it holds only the fields discovery reads, under the service's names.
+    const METADATA: &str = r#"{"models":[{"id":404287,"dbName":"523ab509-8e2d-43ed-bfad-11fcd05180d7"}],"exploration":{"report":{"objectId":"f508555a-b39d-4c10-8d46-a14bc282e079","modelId":404287}}}"#;
+
+    /// A querydata response with a page of allegation counts by command.
+    fn summary_page(dict: &str, counts: &str, restart_tokens: &str) -> String {
+        format!(
+            r#"{{"jobIds":[],"results":[{{"jobId":"1","result":{{"data":{{"timestamp":"","rootActivityId":"","descriptor":{{"Select":[{{"Kind":1,"Depth":0,"Value":"G0","GroupKeys":[],"Name":"Summary.Command"}},{{"Kind":2,"Value":"M0","Name":"CountNonNull(Summary.Complaint ID)"}}],"Expressions":{{"Primary":{{"Groupings":[{{"Keys":[]}}]}}}},"Version":2}},"fromCache":false,"dsr":{{"Version":2,"MinorVersion":1,"DS":[{{"N":"DS0","PH":[{{"DM0":[{{"S":[{{"N":"G0","T":1,"DN":"D0"}},{{"N":"M0","T":4}}],"C":[0,{}]}}]}}],"IC":true{},"ValueDicts":{{"D0":["{}"]}}}}]}}}}}}}}]}}"#,
+            counts, restart_tokens, dict,
+        )
+    }
+
+    fn client(server: &MockServer) -> Client {
+        let unknown = report::Report {
+            model_id: 0,
+            dataset_id: String::new(),
+            report_id: String::new(),
+            ..report::Report::active()
+        };
+
+        Client::from_reqwest(reqwest::Client::new())
+            .with_host(server.uri())
+            .with_retry(retry::RetryPolicy::none())
+            .with_report(query::Database::Active, unknown)
+    }
+
+    #[tokio::test]
+    async fn discover_report() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/public/reports/b2c8d2f2-3ad1-48dc-883c-d4163a6e2d8f/modelsAndExploration"))
+            .and(header("X-PowerBI-ResourceKey", "b2c8d2f2-3ad1-48dc-883c-d4163a6e2d8f"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(METADATA))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let report = client(&server).discover(query::Database::Active).await.expect("discover");
+
+        assert_eq!(report, report::Report::active());
+    }
+
+    #[tokio::test]
+    async fn discover_unknown_model() {
+        let server = MockServer::start().await;
+
+        // The report is built on a model the response doesn't list.
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(METADATA.replace(r#""modelId":404287"#, r#""modelId":1"#)))
+            .mount(&server)
+            .await;
+
+        let err = client(&server).discover(query::Database::Active).await.expect_err("no model");
+
+        assert!(matches!(err, Error::Discovery { missing: "model", .. }));
+    }
+
+    #[tokio::test]
+    async fn schema_of_other_model() {
+        let server = MockServer::start().await;
+
+        // Only another model's schema; the client's report is model 0.
+        Mock::given(method("POST"))
+            .and(path("/public/reports/conceptualschema"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"schemas":[{"schema":{"Version":2,"Entities":[{"Name":"Other","Properties":[]}]},"modelId":404287}]}"#))
+            .mount(&server)
+            .await;
+
+        let err = client(&server).schema(query::Database::Active).await.expect_err("no schema");
+
+        assert!(matches!(err, Error::Discovery { missing: "schema", .. }));
+    }
+
+    #[tokio::test]
+    async fn summary_pages() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/public/reports/querydata"))
+            .and(body_string_contains("RestartTokens"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(summary_page("005 PCT", "3", "")))
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/public/reports/querydata"))
+            .and(body_string_contains(r#""Function":5"#))
+            .respond_with(ResponseTemplate::new(200).set_body_string(summary_page("001 PCT", "12", r#","RT":[["'001 PCT'"]]"#)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let summary = client(&server).summary(query::Database::Active, &[model::SummaryGroup::Command]).await.expect("summary");
+
+        let counts = summary.iter().map(|row| (row.groups[0].as_str(), row.allegations)).collect::<Vec<_>>();
+        assert_eq!(counts, vec![("001 PCT", 12), ("005 PCT", 3)]);
+    }
+
+    #[tokio::test]
+    async fn retry_timeout() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(METADATA).set_delay(Duration::from_secs(5)))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(METADATA))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = client(&server)
+            .with_timeout(Duration::from_millis(200))
+            .with_retry(retry::RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(1),
+                ..retry::RetryPolicy::default()
+            });
+
+        let report = client.discover(query::Database::Active).await.expect("retried");
+
+        assert_eq!(report, report::Report::active());
+    }
+
+    #[tokio::test]
+    async fn discover_without_report() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"models":[{"id":1,"dbName":"x"}]}"#))
+            .mount(&server)
+            .await;
+
+        let err = client(&server).discover(query::Database::Active).await.expect_err("no report");
+
+        assert!(matches!(err, Error::Discovery { missing: "report", .. }));
+    }
+}
@@ -0,0 +1,237 @@
+use std::path::PathBuf;
+
+use crate::{model, query, report, response};
+
+/// Which of the exporter's queries was being made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryKind {
+    /// A page of the officer index.
+    Index,
+    /// One officer's allegation details.
+    Details,
+    /// The public report's metadata.
+    Metadata,
+    /// The conceptual schema of the report's model.
+    Schema,
+    /// A page of allegation counts.
+    Summary,
+}
+
+/// What a failed query was asking for.
+#[derive(Debug, Clone)]
+pub struct QueryContext {
+    pub kind: QueryKind,
+    pub database: query::Database,
+    /// The officer whose details were requested.
+    pub officer_id: Option<String>,
+    /// The restart tokens the index or summary page was requested after.
+    pub restart_tokens: Option<Vec<String>>,
+}
+
+impl QueryContext {
+    pub fn index(database: query::Database, restart_tokens: Option<Vec<String>>) -> Self {
+        QueryContext {
+            kind: QueryKind::Index,
+            database,
+            officer_id: None,
+            restart_tokens,
+        }
+    }
+
+    pub fn metadata(database: query::Database) -> Self {
+        QueryContext {
+            kind: QueryKind::Metadata,
+            database,
+            officer_id: None,
+            restart_tokens: None,
+        }
+    }
+
+    pub fn schema(database: query::Database) -> Self {
+        QueryContext {
+            kind: QueryKind::Schema,
+            ..QueryContext::metadata(database)
+        }
+    }
+
+    pub fn summary(database: query::Database, restart_tokens: Option<Vec<String>>) -> Self {
+        QueryContext {
+            kind: QueryKind::Summary,
+            ..QueryContext::index(database, restart_tokens)
+        }
+    }
+
+    pub fn details(database: query::Database, officer: &model::Officer) -> Self {
+        QueryContext {
+            kind: QueryKind::Details,
+            database,
+            officer_id: Some(officer.id.clone()),
+            restart_tokens: None,
+        }
+    }
+}
+
+impl std::fmt::Display for QueryContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.kind {
+            QueryKind::Index => write!(f, "index query on the {:?} database", self.database)?,
+            QueryKind::Details => write!(f, "details query on the {:?} database", self.database)?,
+            QueryKind::Metadata => write!(f, "metadata query on the {:?} database", self.database)?,
+            QueryKind::Schema => write!(f, "schema query on the {:?} database", self.database)?,
+            QueryKind::Summary => write!(f, "summary query on the {:?} database", self.database)?,
+        }
+
+        if let Some(id) = &self.officer_id {
+            write!(f, " for officer {}", id)?;
+        }
+
+        if let Some(tokens) = &self.restart_tokens {
+            write!(f, " after {:?}", tokens)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Everything that can go wrong while exporting.
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or the response could not be read.
+    Transport {
+        query: QueryContext,
+        source: reqwest::Error,
+    },
+    /// The server answered with an unsuccessful HTTP status.
+    HttpStatus {
+        query: QueryContext,
+        status: reqwest::StatusCode,
+    },
+    /// PowerBI rejected the query.
+    Service {
+        query: QueryContext,
+        source: Box<response::ServiceError>,
+    },
+    /// The response body wasn't the JSON we expected.
+    Json {
+        query: QueryContext,
+        source: serde_json::Error,
+    },
+    /// The response data couldn't be decoded into rows.
+    Decode {
+        query: QueryContext,
+        source: response::DecodeError,
+    },
+    /// A row of the response didn't fit the model.
+    Row {
+        query: QueryContext,
+        source: model::DeserializeError,
+    },
+    /// The report's metadata or schema didn't say what we needed to know.
+    Discovery {
+        query: QueryContext,
+        missing: &'static str,
+    },
+    /// A checkpoint file couldn't be read or written.
+    Checkpoint {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// A report settings file couldn't be read.
+    ReportConfig {
+        path: PathBuf,
+        source: report::ConfigError,
+    },
+    /// An officer couldn't be written as CSV.
+    Csv(csv::Error),
+    /// An officer couldn't be written as JSON.
+    JsonLines(serde_json::Error),
+    /// An officer couldn't be written to SQLite.
+    Sqlite(rusqlite::Error),
+    Io(std::io::Error),
+}
+
+impl Error {
+    /// Whether trying the same query again might succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Transport { source, .. } => !source.is_builder(),
+            Error::HttpStatus { status, .. } => {
+                *status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            Error::Service { source, .. } => source.kind() == response::ServiceErrorKind::Throttled,
+            Error::Json { .. } => true,
+            Error::Decode { .. } | Error::Row { .. } | Error::Discovery { .. } | Error::Checkpoint { .. } | Error::ReportConfig { .. } | Error::Csv(_) | Error::JsonLines(_) | Error::Sqlite(_) | Error::Io(_) => false,
+        }
+    }
+
+    /// The query that failed, if the error came from one.
+    pub fn query(&self) -> Option<&QueryContext> {
+        match self {
+            Error::Transport { query, .. }
+            | Error::HttpStatus { query, .. }
+            | Error::Service { query, .. }
+            | Error::Json { query, .. }
+            | Error::Decode { query, .. }
+            | Error::Row { query, .. }
+            | Error::Discovery { query, .. } => Some(query),
+            Error::Checkpoint { .. } | Error::ReportConfig { .. } | Error::Csv(_) | Error::JsonLines(_) | Error::Sqlite(_) | Error::Io(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Transport { query, source } => write!(f, "{}: {}", query, source),
+            Error::HttpStatus { query, status } => write!(f, "{}: HTTP status {}", query, status),
+            Error::Service { query, source } => write!(f, "{}: {}", query, source),
+            Error::Json { query, source } => write!(f, "{}: malformed response: {}", query, source),
+            Error::Decode { query, source } => write!(f, "{}: undecodable data: {}", query, source),
+            Error::Row { query, source } => write!(f, "{}: {}", query, source),
+            Error::Discovery { query, missing } => write!(f, "{}: response has no {}", query, missing),
+            Error::Checkpoint { path, source } => write!(f, "checkpoint {}: {}", path.display(), source),
+            Error::ReportConfig { path, source } => write!(f, "report settings {}: {}", path.display(), source),
+            Error::Csv(e) => write!(f, "writing CSV: {}", e),
+            Error::JsonLines(e) => write!(f, "writing JSON: {}", e),
+            Error::Sqlite(e) => write!(f, "writing SQLite: {}", e),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport { source, .. } => Some(source),
+            Error::Service { source, .. } => Some(source),
+            Error::Json { source, .. } => Some(source),
+            Error::Decode { source, .. } => Some(source),
+            Error::Row { source, .. } => Some(source),
+            Error::Checkpoint { source, .. } => Some(source),
+            Error::ReportConfig { source, .. } => Some(source),
+            Error::Csv(e) => Some(e),
+            Error::JsonLines(e) => Some(e),
+            Error::Sqlite(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::HttpStatus { .. } | Error::Discovery { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::Csv(e)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Sqlite(e)
+    }
+}
@@ -1,19 +1,23 @@
-use crate::{model, query, response, HOST};
+use crate::error::{Error, QueryContext};
+use crate::{model, query, Client};
 
+/// Pages through the officer index, one officer at a time.
 pub struct Index {
+    columns: Vec<String>,
     items: Vec<Vec<String>>,
     db: query::Database,
     rt: Option<Vec<String>>,
-    client: reqwest::Client,
+    client: Client,
     progress: Option<Vec<String>>,
+    context: QueryContext,
 }
 
 impl Index {
-    pub async fn new(client: reqwest::Client, db: query::Database) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn new(client: Client, db: query::Database) -> Result<Self, Error> {
         Self::new_with_restart_tokens(client, db, None).await
     }
 
-    pub async fn new_after_officer(client: reqwest::Client, db: query::Database, officer: &model::Officer) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn new_after_officer(client: Client, db: query::Database, officer: &model::Officer) -> Result<Self, Error> {
         let restart_tokens = vec![
             query::IntoLiteral::stringify(&officer.command),
             query::IntoLiteral::stringify(&officer.id),
@@ -25,13 +29,15 @@ impl Index {
         Self::new_with_restart_tokens(client, db, Some(restart_tokens)).await
     }
 
-    async fn new_with_restart_tokens(client: reqwest::Client, db: query::Database, rt: Option<Vec<String>>) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn new_with_restart_tokens(client: Client, db: query::Database, rt: Option<Vec<String>>) -> Result<Self, Error> {
         let mut me = Index {
+            columns: vec![],
             items: vec![],
             db,
             rt,
             client,
             progress: None,
+            context: QueryContext::index(db, None),
         };
 
         me.query_more()
@@ -40,36 +46,35 @@ impl Index {
         Ok(me)
     }
 
-    pub async fn next(&mut self) -> Result<Option<model::Officer>, Box<dyn std::error::Error>> {
-        use core::convert::TryFrom;
-
-        if let Some(row) = self.items.pop() {
-            return Ok(Some(model::Officer::try_from(row)?));
-        }
-
-        if self.rt.is_none() {
-            return Ok(None);
+    pub async fn next(&mut self) -> Result<Option<model::Officer>, Error> {
+        if self.items.is_empty() && self.rt.is_some() {
+            self.query_more()
+                .await?;
         }
 
-        self.query_more()
-            .await?;
+        self.items
+            .pop()
+            .map(|row| self.map_row(row))
+            .transpose()
+    }
 
-        let row = self.items.pop();
-        Ok(row.map(|row| model::Officer::try_from(row)).transpose()?)
+    fn map_row(&self, row: Vec<String>) -> Result<model::Officer, Error> {
+        model::Officer::from_row(self.db, model::Row::new(&self.columns, row))
+            .map_err(|source| Error::Row { query: self.context.clone(), source })
     }
 
-    async fn query_more(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let req = query::get_index(&self.db, self.rt.clone());
+    async fn query_more(&mut self) -> Result<(), Error> {
+        let req = query::get_index(self.client.report(self.db), &self.client.index_filter, self.rt.clone(), self.client.window);
+
+        self.context = QueryContext::index(self.db, self.rt.clone());
 
-        let resp = self.client.post(HOST)
-            .header("X-PowerBI-ResourceKey", self.db.get_bi_resource_key())
-            .json(&req)
-            .send()
-            .await?
-            .json::<response::Response>()
+        let resp = self.client.query(&self.context, &req)
             .await?;
 
-        self.items = resp.get_data();
+        self.columns = resp.get_columns()
+            .map_err(|source| Error::Decode { query: self.context.clone(), source })?;
+        self.items = resp.get_data()
+            .map_err(|source| Error::Decode { query: self.context.clone(), source })?;
         self.items.reverse();
 
         self.rt = resp.get_restart_tokens();
@@ -78,47 +83,48 @@ impl Index {
         Ok(())
     }
 
+    /// The restart tokens of the page just fetched, if a new page was
+    /// fetched since the last call.
     pub fn progress(&mut self) -> Option<Vec<String>> {
         self.progress.take()
     }
 }
 
+/// The allegation details of a single officer.
 pub struct Details {
-    officer_id: String,
+    officer: model::Officer,
+    columns: Vec<String>,
     items: Vec<Vec<String>>,
+    context: QueryContext,
 }
 
 impl Details {
-    pub async fn new(client: &reqwest::Client, db: query::Database, officer: &model::Officer) -> Result<Self, Box<dyn std::error::Error>> {
-        let req = query::get_followup(&db, officer);
-
-        let resp = client.post(HOST)
-            .header("X-PowerBI-ResourceKey", db.get_bi_resource_key())
-            .json(&req)
-            .send()
-            .await?
-            .json::<response::Response>()
+    pub async fn new(client: &Client, db: query::Database, officer: &model::Officer) -> Result<Self, Error> {
+        let req = query::get_followup(client.report(db), officer, &client.details_filter);
+        let context = QueryContext::details(db, officer);
+
+        let resp = client.query(&context, &req)
             .await?;
 
-        let mut items = resp.get_data();
+        let columns = resp.get_columns()
+            .map_err(|source| Error::Decode { query: context.clone(), source })?;
+        let mut items = resp.get_data()
+            .map_err(|source| Error::Decode { query: context.clone(), source })?;
         items.reverse();
 
-        Ok(Details { items, officer_id: officer.id.clone() })
+        Ok(Details { items, columns, officer: officer.clone(), context })
     }
 }
 
 impl Iterator for Details {
-    type Item = Result<model::Details, model::DeserializeError>;
+    type Item = Result<model::Details, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        use core::convert::TryFrom;
-
         self.items
             .pop()
-            .map(model::Details::try_from)
-            .map(|res| res.map(|mut i| {
-                i.officer_id = self.officer_id.clone();
-                i
-            }))
+            .map(|row| {
+                model::Details::from_row(&self.officer, model::Row::new(&self.columns, row))
+                    .map_err(|source| Error::Row { query: self.context.clone(), source })
+            })
     }
 }
@@ -0,0 +1,48 @@
+//! Export the Member of Service Histories from the NYC CCRB.
+//!
+//! The CCRB publishes its MOS records through a public PowerBI report.
+//! This crate speaks just enough of the PowerBI query protocol to page
+//! through the officer index and pull each officer's allegations.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = ccrb_export::Client::new()?;
+//! let db = ccrb_export::Database::Inactive;
+//!
+//! let mut officers = client.index(db).await?;
+//! while let Some(officer) = officers.next().await? {
+//!     for details in client.details(db, &officer).await? {
+//!         println!("{:?}", details?);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod checkpoint;
+pub mod client;
+pub mod error;
+pub mod iter;
+pub mod model;
+pub mod output;
+pub mod query;
+pub mod report;
+pub mod response;
+pub mod retry;
+pub mod schema;
+
+pub use checkpoint::Checkpoint;
+pub use client::Client;
+pub use error::{Error, QueryContext, QueryKind};
+pub use model::{BoardDisposition, DeserializeError, Details, FadoType, NypdDisposition, Officer, Row, Summary, SummaryGroup};
+pub use output::Sink;
+pub use query::Database;
+pub use report::{Report, Reports};
+pub use response::{DecodeError, ServiceError, ServiceErrorKind};
+pub use retry::RetryPolicy;
+
+/// The PowerBI cluster serving the public CCRB reports.
+pub const HOST: &str = "https://wabi-us-gov-virginia-api.analysis.usgovcloudapi.net";
+
+/// The user agent sent with every request.
+pub const USER_AGENT: &str = "User-Agent: Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:84.0) Gecko/20100101 Firefox/84.0";
@@ -1,81 +1,767 @@
-mod iter;
-mod model;
-mod query;
-mod response;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
-const HOST: &str = "https://wabi-us-gov-virginia-api.analysis.usgovcloudapi.net/public/reports/querydata?synchronous=true";
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use futures::stream::{FuturesOrdered, StreamExt};
 
-const USER_AGENT: &str = "User-Agent: Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:84.0) Gecko/20100101 Firefox/84.0";
+use ccrb_export::checkpoint::Progress;
+use ccrb_export::report::Embed;
+use ccrb_export::{model, output, query, Checkpoint, Client, Database, Reports, RetryPolicy, Sink};
+
+/// Export the Member of Service Histories from the NYC CCRB.
+#[derive(Parser, Debug)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    /// Log more detail; repeat for even more
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Log less detail; repeat for even less
+    #[arg(short, long, action = clap::ArgAction::Count, global = true, conflicts_with = "verbose")]
+    quiet: u8,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    export: ExportArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Download the officer index and every officer's allegations (the default)
+    Export(Box<ExportArgs>),
+    /// List the entities, columns and measures of a database's model
+    Schema(SchemaArgs),
+    /// Count allegations by command, FADO type, disposition and so on
+    Summary(Box<SummaryArgs>),
+}
+
+#[derive(Args, Debug)]
+struct ExportArgs {
+    /// Which database to export
+    #[arg(short, long, value_enum, default_value_t = DatabaseArg::Inactive)]
+    database: DatabaseArg,
+
+    /// Directory to write the output files into
+    #[arg(short, long, default_value = ".")]
+    output_dir: PathBuf,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+
+    /// Nest each officer's allegations in the officer record (JSON Lines only)
+    #[arg(long)]
+    nest_allegations: bool,
+
+    /// File name for the officer index [default: officers.csv or officers.jsonl]
+    #[arg(long)]
+    officers_file: Option<PathBuf>,
+
+    /// File name for the allegation details [default: details.csv or details.jsonl]
+    #[arg(long)]
+    details_file: Option<PathBuf>,
+
+    /// File name for the SQLite database
+    #[arg(long, default_value = "ccrb.sqlite")]
+    sqlite_file: PathBuf,
+
+    /// File name for the ids found in both databases (with --database all)
+    #[arg(long, default_value = "officers_in_both.csv")]
+    in_both_file: PathBuf,
+
+    /// Minimum milliseconds between requests, across all workers
+    #[arg(long, default_value_t = 25)]
+    delay: u64,
+
+    /// Attempts per query before giving up on a transient error
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// Milliseconds to wait before the first retry; doubles on each retry
+    #[arg(long, default_value_t = 500)]
+    backoff: u64,
+
+    /// Longest wait between retries, in milliseconds
+    #[arg(long, default_value_t = 30_000)]
+    max_backoff: u64,
+
+    /// Milliseconds a request may take before it's given up on and retried
+    #[arg(long, default_value_t = 60_000, value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+
+    /// Number of officers' details to fetch at once
+    #[arg(short = 'j', long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    concurrency: u16,
+
+    /// Number of officers to fetch per page of the index
+    #[arg(long, default_value_t = query::DEFAULT_WINDOW, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    window: usize,
+
+    #[command(flatten)]
+    filter: IndexFilterArgs,
+
+    #[command(flatten)]
+    details_filter: DetailsFilterArgs,
+
+    #[command(flatten)]
+    report: ReportArgs,
+
+    /// Checkpoint file [default: OUTPUT_DIR/checkpoint.json]
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Continue an interrupted export from its checkpoint
+    #[arg(long)]
+    resume: bool,
+}
+
+#[derive(Args, Debug)]
+struct SchemaArgs {
+    /// Which database's model to describe
+    #[arg(short, long, value_enum, default_value_t = DatabaseArg::Inactive)]
+    database: DatabaseArg,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = SchemaFormat::Table)]
+    format: SchemaFormat,
+
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+#[derive(Args, Debug)]
+struct SummaryArgs {
+    /// Which database's allegations to count
+    #[arg(short, long, value_enum, default_value_t = DatabaseArg::Inactive)]
+    database: DatabaseArg,
+
+    /// Count allegations by these, in order; repeat or separate with commas
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [GroupArg::Command, GroupArg::FadoType, GroupArg::BoardDisposition])]
+    by: Vec<GroupArg>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = SummaryFormat::Table)]
+    format: SummaryFormat,
+
+    /// Number of groups to fetch per request
+    #[arg(long, default_value_t = query::DEFAULT_WINDOW, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    window: usize,
+
+    #[command(flatten)]
+    filter: IndexFilterArgs,
+
+    #[command(flatten)]
+    details_filter: DetailsFilterArgs,
+
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+/// Which officers to include.  Repeat an option to match any of its values.
+#[derive(Args, Debug)]
+struct IndexFilterArgs {
+    /// Only include officers of this command, e.g. "075 PCT"
+    #[arg(long = "command", value_name = "COMMAND")]
+    commands: Vec<String>,
+
+    /// Only include officers of this rank, e.g. "Police Officer"
+    #[arg(long = "rank", value_name = "RANK")]
+    ranks: Vec<String>,
+
+    /// Only include officers whose last name starts with this
+    #[arg(long, value_name = "PREFIX")]
+    last_name_prefix: Option<String>,
+
+    /// Only include the officer with this shield number
+    #[arg(long = "shield", value_name = "SHIELD_NO")]
+    shield_numbers: Vec<String>,
+}
+
+impl IndexFilterArgs {
+    fn filter(&self) -> query::IndexFilter {
+        query::IndexFilter {
+            commands: self.commands.clone(),
+            ranks: self.ranks.clone(),
+            last_name_prefix: self.last_name_prefix.clone(),
+            shield_numbers: self.shield_numbers.clone(),
+        }
+    }
+}
+
+/// Which allegations to include.  Repeat an option to match any of its values.
+#[derive(Args, Debug)]
+struct DetailsFilterArgs {
+    /// Only include allegations of incidents on or after this date, e.g. 2018-01-01
+    #[arg(long, value_name = "DATE")]
+    since: Option<chrono::NaiveDate>,
+
+    /// Only include allegations of incidents on or before this date
+    #[arg(long, value_name = "DATE")]
+    until: Option<chrono::NaiveDate>,
+
+    /// Only include allegations of this FADO type, e.g. "Force"
+    #[arg(long = "fado-type", value_name = "TYPE")]
+    fado_types: Vec<model::FadoType>,
+
+    /// Only include allegations with this board disposition; "Substantiated"
+    /// matches any recommended discipline
+    #[arg(long = "board-disposition", value_name = "DISPOSITION")]
+    board_dispositions: Vec<model::BoardDisposition>,
+
+    /// Only include allegations with this NYPD disposition, e.g. "Charges"
+    #[arg(long = "nypd-disposition", value_name = "DISPOSITION")]
+    nypd_dispositions: Vec<model::NypdDisposition>,
+}
+
+impl DetailsFilterArgs {
+    fn filter(&self) -> query::DetailsFilter {
+        query::DetailsFilter {
+            since: self.since,
+            until: self.until,
+            fado_types: self.fado_types.clone(),
+            board_dispositions: self.board_dispositions.clone(),
+            nypd_dispositions: self.nypd_dispositions.clone(),
+        }
+    }
+}
+
+/// Where to find each database's report.
+#[derive(Args, Debug)]
+struct ReportArgs {
+    /// TOML (or .json) file overriding where each database is published
+    #[arg(long)]
+    report_config: Option<PathBuf>,
+
+    /// Public app.powerbigov.us/view?r=… URL of the active database's report
+    #[arg(long, value_name = "URL")]
+    active_url: Option<String>,
+
+    /// Public app.powerbigov.us/view?r=… URL of the inactive database's report
+    #[arg(long, value_name = "URL")]
+    inactive_url: Option<String>,
+
+    /// Look up each report's model, dataset and report ids from its resource key
+    #[arg(long)]
+    discover: bool,
+}
+
+impl ReportArgs {
+    /// Where each database is published, according to these arguments.
+    fn reports(&self) -> Result<Reports, Box<dyn std::error::Error>> {
+        let mut reports = match &self.report_config {
+            Some(path) => Reports::load(path)?,
+            None => Reports::default(),
+        };
+
+        for (db, url) in [(Database::Active, &self.active_url), (Database::Inactive, &self.inactive_url)] {
+            if let Some(url) = url {
+                let embed = Embed::parse(url)?;
+                log::debug!("{:?} report resource key {} in tenant {}", db, embed.resource_key, embed.tenant_id);
+
+                let report = reports.get_mut(db);
+                *report = report.clone().with_embed(&embed);
+            }
+        }
+
+        Ok(reports)
+    }
+
+    /// Look up the ids of the given databases' reports, if asked to.
+    async fn discover(&self, mut client: Client, databases: &[Database]) -> Result<Client, ccrb_export::Error> {
+        if self.discover {
+            for &db in databases {
+                let report = client.discover(db).await?;
+                log::info!("{:?} database is model {}, dataset {}, report {}", db, report.model_id, report.dataset_id, report.report_id);
+
+                client = client.with_report(db, report);
+            }
+        }
+
+        Ok(client)
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SchemaFormat {
+    /// Aligned columns
+    Table,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SummaryFormat {
+    /// Aligned columns
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GroupArg {
+    Command,
+    Rank,
+    FadoType,
+    BoardDisposition,
+    NypdDisposition,
+}
+
+impl GroupArg {
+    fn group(self) -> model::SummaryGroup {
+        match self {
+            GroupArg::Command => model::SummaryGroup::Command,
+            GroupArg::Rank => model::SummaryGroup::Rank,
+            GroupArg::FadoType => model::SummaryGroup::FadoType,
+            GroupArg::BoardDisposition => model::SummaryGroup::BoardDisposition,
+            GroupArg::NypdDisposition => model::SummaryGroup::NypdDisposition,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Csv,
+    /// JSON Lines
+    Jsonl,
+    /// Normalized officers, complaints and allegations tables
+    Sqlite,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Jsonl => "jsonl",
+            Format::Sqlite => "sqlite",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum DatabaseArg {
+    Active,
+    Inactive,
+    /// Both databases; an export crawls them side by side
+    #[value(alias = "both")]
+    All,
+}
+
+impl DatabaseArg {
+    fn databases(self) -> Vec<Database> {
+        match self {
+            DatabaseArg::Active => vec![Database::Active],
+            DatabaseArg::Inactive => vec![Database::Inactive],
+            DatabaseArg::All => vec![Database::Active, Database::Inactive],
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert("Accept", reqwest::header::HeaderValue::from_static("application/json, text/plain, */*"));
-    headers.insert("ActivityId", reqwest::header::HeaderValue::from_static("a366f021-d490-ed01-6681-0fe32cf1255a"));
-    headers.insert("RequestId", reqwest::header::HeaderValue::from_static("141bd242-9744-e182-52a8-ed8a1633b878"));
-    headers.insert("Content-Type", reqwest::header::HeaderValue::from_static("application/json;charset=UTF-8"));
-    headers.insert("Origin", reqwest::header::HeaderValue::from_static("https://app.powerbigov.us"));
-    headers.insert("Connection", reqwest::header::HeaderValue::from_static("keep-alive"));
-    headers.insert("Referer", reqwest::header::HeaderValue::from_static("https://app.powerbigov.us/view?r=eyJrIjoiYjJjOGQyZjItM2FkMS00OGRjLTg4M2MtZDQxNjNhNmUyZDhmIiwidCI6IjczZDYxNzk5LWMyODQtNDAyMi04ZDQxLTU0Y2M0ZjE5MjllZiJ9"));
-    headers.insert("Sec-GPC", reqwest::header::HeaderValue::from_static("1"));
-    headers.insert("Pragma", reqwest::header::HeaderValue::from_static("no-cache"));
-    headers.insert("Cache-Control", reqwest::header::HeaderValue::from_static("no-cache"));
-
-    let client = reqwest::Client::builder()
-        .user_agent(USER_AGENT)
-        .default_headers(headers)
-        .build()?;
-
-    let mut windex = csv::Writer::from_writer(
-        std::io::BufWriter::new(
-            std::fs::File::create(
-                "./officers.csv",
-            )?,
-        ),
+    let cli = Cli::parse();
+
+    let level = match i16::from(cli.verbose) - i16::from(cli.quiet) {
+        i16::MIN..=-2 => log::LevelFilter::Error,
+        -1 => log::LevelFilter::Warn,
+        0 => log::LevelFilter::Info,
+        1 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .init();
+
+    match cli.command {
+        Some(Command::Export(args)) => export(*args).await,
+        Some(Command::Schema(args)) => schema(args).await,
+        Some(Command::Summary(args)) => summary(*args).await,
+        None => export(cli.export).await,
+    }
+}
+
+async fn export(args: ExportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let reports = args.report.reports()?;
+    let index_filter = args.filter.filter();
+    let details_filter = args.details_filter.filter();
+
+    let client = Client::new()?
+        .with_reports(reports)
+        .with_index_filter(index_filter.clone())
+        .with_details_filter(details_filter.clone())
+        .with_window(args.window)
+        .with_min_interval(std::time::Duration::from_millis(args.delay))
+        .with_timeout(std::time::Duration::from_millis(args.timeout))
+        .with_retry(RetryPolicy {
+            max_attempts: args.max_attempts,
+            initial_backoff: std::time::Duration::from_millis(args.backoff),
+            max_backoff: std::time::Duration::from_millis(args.max_backoff),
+            ..RetryPolicy::default()
+        });
+
+    let client = args.report.discover(client, &args.database.databases()).await?;
+
+    std::fs::create_dir_all(&args.output_dir)?;
+
+    let checkpoint_path = args.checkpoint.clone()
+        .unwrap_or_else(|| args.output_dir.join("checkpoint.json"));
+    let officers_path = args.output_dir.join(
+        args.officers_file.clone()
+            .unwrap_or_else(|| format!("officers.{}", args.format.extension()).into()),
     );
-    let mut wdetails = csv::Writer::from_writer(
-        std::io::BufWriter::new(
-            std::fs::File::create(
-                "./details.csv",
-            )?,
-        ),
+    let details_path = args.output_dir.join(
+        args.details_file.clone()
+            .unwrap_or_else(|| format!("details.{}", args.format.extension()).into()),
     );
 
-    let db = query::Database::Inactive;
+    let sqlite_path = args.output_dir.join(&args.sqlite_file);
 
-    let mut records = iter::Index::new(client.clone(), db).await?;
+    if args.nest_allegations && !matches!(args.format, Format::Jsonl) {
+        return Err("--nest-allegations needs --format jsonl".into());
+    }
+
+    let resume = if args.resume {
+        let checkpoint = Checkpoint::load(&checkpoint_path)?;
+        if checkpoint.is_none() {
+            log::warn!("no checkpoint at {}, starting from the beginning", checkpoint_path.display());
+        }
+        checkpoint
+    } else {
+        None
+    };
 
-    let mut count = 0;
+    let (mut sink, outputs): (Box<dyn Sink>, Vec<&PathBuf>) = match args.format {
+        Format::Csv => {
+            let (officers, officers_empty) = open_output(&officers_path, resume.as_ref())?;
+            let (details, details_empty) = open_output(&details_path, resume.as_ref())?;
+
+            let sink = output::Csv::new(
+                csv::WriterBuilder::new().has_headers(officers_empty).from_writer(officers),
+                csv::WriterBuilder::new().has_headers(details_empty).from_writer(details),
+            );
+
+            (Box::new(sink), vec![&officers_path, &details_path])
+        }
+        Format::Jsonl if args.nest_allegations => {
+            let (officers, _) = open_output(&officers_path, resume.as_ref())?;
+
+            (Box::new(output::JsonLines::nested(officers)), vec![&officers_path])
+        }
+        Format::Jsonl => {
+            let (officers, _) = open_output(&officers_path, resume.as_ref())?;
+            let (details, _) = open_output(&details_path, resume.as_ref())?;
+
+            (Box::new(output::JsonLines::flat(officers, details)), vec![&officers_path, &details_path])
+        }
+        Format::Sqlite => {
+            // Rows are upserted, so there's nothing to roll back on resume.
+            if resume.is_none() && sqlite_path.exists() {
+                std::fs::remove_file(&sqlite_path)?;
+            }
+
+            (Box::new(output::Sqlite::open(&sqlite_path)?), vec![])
+        }
+    };
+
+    let databases = args.database.databases();
+    if let Some(checkpoint) = &resume {
+        if let Some(progress) = checkpoint.databases.iter().find(|progress| !databases.contains(&progress.database)) {
+            return Err(format!("checkpoint is for the {:?} database, which isn't being exported", progress.database).into());
+        }
+        if checkpoint.index_filter != index_filter {
+            return Err("checkpoint was made with other officer filters (--command, --rank, --last-name-prefix, --shield)".into());
+        }
+        if checkpoint.details_filter != details_filter {
+            return Err("checkpoint was made with other allegation filters (--since, --until, --fado-type, --board-disposition, --nypd-disposition)".into());
+        }
+    }
+
+    // The databases' pagers take turns feeding the same pipeline, so with
+    // more than one worker they're crawled concurrently.
+    let mut pagers = vec![];
+    let mut progress = vec![];
+
+    for &db in &databases {
+        let resumed = resume.as_ref().and_then(|checkpoint| checkpoint.progress(db));
+
+        let records = match resumed.and_then(|progress| progress.last_officer.as_ref()) {
+            Some(officer) => {
+                log::info!("resuming {:?} database after id {}", db, officer.id);
+                client.index_after_officer(db, officer).await?
+            }
+            None => {
+                log::info!("exporting {:?} database", db);
+                client.index(db).await?
+            }
+        };
+
+        pagers.push((db, records));
+        progress.push(resumed.cloned().unwrap_or(Progress {
+            database: db,
+            restart_tokens: None,
+            last_officer: None,
+        }));
+    }
+
+    // Only worth keeping track of with more than one database, since the
+    // record of it goes in every checkpoint.
+    let track_overlap = databases.len() > 1;
+    let mut overlap = resume.as_ref().map(|checkpoint| checkpoint.overlap.clone()).unwrap_or_default();
 
     let mut rows = 0;
+    let mut turn = 0;
+
+    let mut pending = FuturesOrdered::new();
+
+    loop {
+        while !pagers.is_empty() && pending.len() < usize::from(args.concurrency) {
+            let i = turn % pagers.len();
+            let (db, records) = &mut pagers[i];
+
+            match records.next().await? {
+                Some(officer) => {
+                    let page = records.progress();
+                    pending.push_back(fetch_details(client.clone(), *db, officer, page));
+                    turn += 1;
+                }
+                None => {
+                    log::info!("finished listing the {:?} database", db);
+                    pagers.remove(i);
+                }
+            }
+        }
+
+        let (officer, details, page) = match pending.next().await {
+            Some(fetched) => fetched?,
+            None => break,
+        };
 
-    while let Some(officer) = records.next().await? {
         rows += 1;
         if rows % 10 == 0 {
-            println!("...to id {}", officer.id);
+            log::info!("...to id {}", officer.id);
+        }
+
+        sink.write(&officer, &details)?;
+
+        if track_overlap {
+            overlap.record(&officer);
+        }
+
+        let db_progress = progress.iter_mut()
+            .find(|progress| progress.database == officer.status)
+            .expect("progress for every database");
+        db_progress.last_officer = Some(officer);
+
+        if let Some(tokens) = page {
+            log::debug!("querying to {:?}", tokens);
+            db_progress.restart_tokens = Some(tokens);
+
+            sink.flush()?;
+
+            Checkpoint {
+                databases: progress.clone(),
+                output_lengths: output_lengths(&outputs)?,
+                index_filter: index_filter.clone(),
+                details_filter: details_filter.clone(),
+                overlap: overlap.clone(),
+            }.save(&checkpoint_path)?;
         }
+    }
 
-        windex.serialize(&officer)?;
+    sink.flush()?;
 
-        tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+    if track_overlap {
+        let in_both_path = args.output_dir.join(&args.in_both_file);
+        overlap.write_in_both(std::fs::File::create(&in_both_path)?)?;
 
-        for details in iter::Details::new(&client, db, &officer).await? {
-            wdetails.serialize(details?)?;
+        if !overlap.in_both.is_empty() {
+            log::warn!(
+                "{} officer ids appear in both the Active and Inactive databases; see {}",
+                overlap.in_both.len(),
+                in_both_path.display(),
+            );
         }
+    }
 
-        if let Some(tokens) = records.progress() {
-            println!("querying to {:?}", tokens);
+    if checkpoint_path.exists() {
+        std::fs::remove_file(&checkpoint_path)?;
+    }
+
+    Ok(())
+}
 
-            count += 1;
-            if count % 10 == 0 {
-                windex.flush()?;
-                wdetails.flush()?;
+async fn schema(args: SchemaArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let databases = args.database.databases();
+
+    let client = Client::new()?
+        .with_reports(args.report.reports()?);
+    let client = args.report.discover(client, &databases).await?;
+
+    let mut schemas = vec![];
+    for db in databases {
+        schemas.push((db, client.schema(db).await?));
+    }
+
+    match args.format {
+        SchemaFormat::Json => {
+            let json = schemas.iter()
+                .map(|(db, entities)| serde_json::json!({ "database": db, "entities": entities }))
+                .collect::<Vec<_>>();
+
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        SchemaFormat::Table => {
+            let mut rows = vec![vec!["DATABASE".to_string(), "ENTITY".into(), "PROPERTY".into(), "KIND".into(), "TYPE".into()]];
+
+            for (db, entities) in &schemas {
+                for entity in entities {
+                    for property in &entity.properties {
+                        let hidden = if entity.hidden || property.hidden { " (hidden)" } else { "" };
+
+                        rows.push(vec![
+                            db.as_str().into(),
+                            entity.name.clone(),
+                            format!("{}{}", property.name, hidden),
+                            property.kind.as_str().into(),
+                            property.data_type.clone().unwrap_or_default(),
+                        ]);
+                    }
+                }
             }
+
+            print_table(&rows);
         }
     }
 
-    windex.flush()?;
-    wdetails.flush()?;
+    Ok(())
+}
+
+async fn summary(args: SummaryArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let databases = args.database.databases();
+    let group_by = args.by.iter().map(|arg| arg.group()).collect::<Vec<_>>();
+
+    let client = Client::new()?
+        .with_reports(args.report.reports()?)
+        .with_window(args.window)
+        .with_index_filter(args.filter.filter())
+        .with_details_filter(args.details_filter.filter());
+    let client = args.report.discover(client, &databases).await?;
+
+    let mut summary = vec![];
+    for db in databases {
+        summary.extend(client.summary(db, &group_by).await?);
+    }
+
+    let header = std::iter::once("database")
+        .chain(group_by.iter().map(|group| group.as_str()))
+        .chain(std::iter::once("allegations"))
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    let rows = summary.iter()
+        .map(|row| {
+            std::iter::once(row.status.as_str().to_string())
+                .chain(row.groups.iter().cloned())
+                .chain(std::iter::once(row.allegations.to_string()))
+                .collect::<Vec<_>>()
+        });
+
+    match args.format {
+        SummaryFormat::Json => {
+            let json = summary.iter()
+                .map(|row| {
+                    let mut object = serde_json::Map::new();
+                    object.insert("database".into(), serde_json::json!(row.status));
+                    for (group, value) in group_by.iter().zip(&row.groups) {
+                        object.insert(group.as_str().into(), serde_json::json!(value));
+                    }
+                    object.insert("allegations".into(), serde_json::json!(row.allegations));
+                    serde_json::Value::Object(object)
+                })
+                .collect::<Vec<_>>();
+
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        SummaryFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout());
+            writer.write_record(&header)?;
+            for row in rows {
+                writer.write_record(&row)?;
+            }
+            writer.flush()?;
+        }
+        SummaryFormat::Table => {
+            let header = header.iter()
+                .map(|name| name.replace('_', " ").to_uppercase())
+                .collect();
+
+            print_table(&std::iter::once(header).chain(rows).collect::<Vec<_>>());
+        }
+    }
 
     Ok(())
 }
+
+/// Print rows as left-aligned columns, the first row being the header.
+fn print_table(rows: &[Vec<String>]) {
+    let mut widths = vec![0; rows.first().map_or(0, Vec::len)];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in rows {
+        let line = row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Fetch one officer's details, passing along the officer and the index
+/// progress so results can be written in order.
+async fn fetch_details(
+    client: Client,
+    db: Database,
+    officer: model::Officer,
+    progress: Option<Vec<String>>,
+) -> Result<(model::Officer, Vec<model::Details>, Option<Vec<String>>), ccrb_export::Error> {
+    let details = client.details(db, &officer)
+        .await?
+        .collect::<Result<_, _>>()?;
+    Ok((officer, details, progress))
+}
+
+/// Open an output file, or when resuming, truncate it back to the
+/// checkpoint so records written after the checkpoint aren't duplicated.
+/// Also says whether the file starts out empty.
+fn open_output(path: &Path, resume: Option<&Checkpoint>) -> Result<(std::io::BufWriter<std::fs::File>, bool), Box<dyn std::error::Error>> {
+    let (file, empty) = match resume {
+        Some(checkpoint) => {
+            let length = checkpoint.output_length(path)
+                .ok_or_else(|| format!("checkpoint has no record of {}", path.display()))?;
+
+            let file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(path)?;
+            file.set_len(length)?;
+
+            (file, length == 0)
+        }
+        None => (std::fs::File::create(path)?, true),
+    };
+
+    Ok((std::io::BufWriter::new(file), empty))
+}
+
+/// The current length of each (flushed) output file.
+fn output_lengths(paths: &[&PathBuf]) -> Result<BTreeMap<PathBuf, u64>, std::io::Error> {
+    paths.iter()
+        .map(|path| Ok(((*path).clone(), std::fs::metadata(path)?.len())))
+        .collect()
+}
@@ -1,82 +1,375 @@
-use serde::Serialize;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+use crate::query::Database;
+
+/// The names the index query gives the officer columns.
+pub mod officer_columns {
+    pub const ID: &str = "Query1.Unique Id";
+    pub const COMMAND: &str = "Query1.Command1";
+    pub const LAST_NAME: &str = "Query1.Last Name1";
+    pub const FIRST_NAME: &str = "Query1.First Name1";
+    pub const RANK: &str = "Query1.Rank1";
+    pub const SHIELD_NO: &str = "Query1.ShieldNo";
+}
+
+/// The names the details query gives the allegation columns.
+pub mod details_columns {
+    pub const INDEX: &str = "Sum(Query1.Rn)";
+    pub const COMPLAINT_ID: &str = "CountNonNull(Query1.Complaint Id)1";
+    pub const INCIDENT_DATE: &str = "Query1.Incident Date";
+    pub const FADO_TYPE: &str = "Query1.FADO Type1";
+    pub const ALLEGATION: &str = "Query1.Allegation1";
+    pub const BOARD_DISPOSITION: &str = "Query1.Board Disposition1";
+    pub const NYPD_DISPOSITION: &str = "Query1.NYPD Disposition";
+    pub const PENALTY: &str = "Query1.PenaltyDesc1";
+}
+
+/// The names the summary query gives its columns.
+pub mod summary_columns {
+    pub const COMMAND: &str = "Summary.Command";
+    pub const RANK: &str = "Summary.Rank";
+    pub const FADO_TYPE: &str = "Summary.FADO Type";
+    pub const BOARD_DISPOSITION: &str = "Summary.Board Disposition";
+    pub const NYPD_DISPOSITION: &str = "Summary.NYPD Disposition";
+    pub const ALLEGATIONS: &str = "CountNonNull(Summary.Complaint ID)";
+}
+
+/// One decoded row of a response, with its values keyed by column name.
+pub struct Row<'a> {
+    columns: &'a [String],
+    values: Vec<String>,
+}
+
+impl<'a> Row<'a> {
+    /// Pair up `values` with the `columns` named by the response.
+    pub fn new(columns: &'a [String], values: Vec<String>) -> Self {
+        Row { columns, values }
+    }
+
+    /// Take the value of the named column out of the row.
+    fn take(&mut self, column: &'static str) -> Result<String, DeserializeError> {
+        self.columns
+            .iter()
+            .position(|c| c == column)
+            .and_then(|i| self.values.get_mut(i))
+            .map(std::mem::take)
+            .ok_or(DeserializeError::MissingColumn(column))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Officer {
     pub id: String,
     pub command: String,
     pub last_name: String,
     pub first_name: String,
     pub rank: String,
+    /// The shield number exactly as the report has it; see `shield_number`.
     pub shield_no: String,
+    /// The database the officer was found in.
+    pub status: Database,
 }
 
-impl core::convert::TryFrom<Vec<String>> for Officer {
-    type Error = DeserializeError;
-
-    fn try_from(mut row: Vec<String>) -> Result<Self, Self::Error> {
-        let shield_no = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let rank = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let first_name = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let last_name = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let command = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let id = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-
-        if !row.is_empty() {
-            return Err(DeserializeError::TooManyColumns);
-        }
+impl Officer {
+    /// The shield number, if the officer has a numeric one.
+    pub fn shield_number(&self) -> Option<u32> {
+        self.shield_no.trim().parse().ok()
+    }
+}
 
-        Ok(Officer { id, command, last_name, first_name, rank, shield_no })
+impl Officer {
+    /// Read an officer from a row of the `status` database's index.
+    pub fn from_row(status: Database, mut row: Row) -> Result<Self, DeserializeError> {
+        Ok(Officer {
+            id: row.take(officer_columns::ID)?,
+            command: row.take(officer_columns::COMMAND)?,
+            last_name: row.take(officer_columns::LAST_NAME)?,
+            first_name: row.take(officer_columns::FIRST_NAME)?,
+            rank: row.take(officer_columns::RANK)?,
+            shield_no: row.take(officer_columns::SHIELD_NO)?,
+            status,
+        })
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Details {
     pub officer_id: String,
-    pub index: String,
+    pub index: u32,
     pub complaint_id: String,
-    pub incident_date: String,
-    pub fado_type: String,
+    pub incident_date: Option<NaiveDate>,
+    pub fado_type: FadoType,
     pub allegation: String,
-    pub board_disposition: String,
-    pub nypd_disposition: String,
+    pub board_disposition: BoardDisposition,
+    pub nypd_disposition: Option<NypdDisposition>,
     pub penalty: String,
+    /// The database the officer was found in.
+    pub status: Database,
 }
 
-impl core::convert::TryFrom<Vec<String>> for Details {
-    type Error = DeserializeError;
+impl Details {
+    /// Read one of `officer`'s allegations from a row of their details.
+    pub fn from_row(officer: &Officer, mut row: Row) -> Result<Self, DeserializeError> {
+        let index = row.take(details_columns::INDEX)?;
+        let complaint_id = row.take(details_columns::COMPLAINT_ID)?;
+        let incident_date = row.take(details_columns::INCIDENT_DATE)?;
+        let fado_type = row.take(details_columns::FADO_TYPE)?;
+        let allegation = row.take(details_columns::ALLEGATION)?;
+        let board_disposition = row.take(details_columns::BOARD_DISPOSITION)?;
+        let nypd_disposition = row.take(details_columns::NYPD_DISPOSITION)?;
+        let penalty = row.take(details_columns::PENALTY)?;
 
-    fn try_from(mut row: Vec<String>) -> Result<Self, Self::Error> {
-        let penalty = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let nypd_disposition = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let board_disposition = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let allegation = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let fado_type = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let incident_date = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let complaint_id = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
-        let index = row.pop().ok_or(DeserializeError::NotEnoughColumns)?;
+        let index = index.parse()
+            .map_err(|_| DeserializeError::InvalidValue { column: "index", value: index })?;
 
-        if !row.is_empty() {
-            return Err(DeserializeError::TooManyColumns);
-        }
+        let incident_date = match incident_date.as_str() {
+            "" => None,
+            date => Some(NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| DeserializeError::InvalidValue { column: "incident_date", value: incident_date.clone() })?),
+        };
+
+        let nypd_disposition = match nypd_disposition.as_str() {
+            "" => None,
+            _ => Some(nypd_disposition.into()),
+        };
 
         Ok(Details {
-            officer_id: String::new(), // TODO: this is awkward
+            officer_id: officer.id.clone(),
             index,
             complaint_id,
             incident_date,
-            fado_type,
+            fado_type: fado_type.into(),
             allegation,
-            board_disposition,
+            board_disposition: board_disposition.into(),
             nypd_disposition,
             penalty,
+            status: officer.status,
         })
     }
 }
 
+/// A property the summary can count allegations by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryGroup {
+    Command,
+    Rank,
+    FadoType,
+    BoardDisposition,
+    NypdDisposition,
+}
+
+impl SummaryGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SummaryGroup::Command => "command",
+            SummaryGroup::Rank => "rank",
+            SummaryGroup::FadoType => "fado_type",
+            SummaryGroup::BoardDisposition => "board_disposition",
+            SummaryGroup::NypdDisposition => "nypd_disposition",
+        }
+    }
+
+    /// The property of the report's entity.
+    pub fn property(&self) -> &'static str {
+        match self {
+            SummaryGroup::Command => "Command",
+            SummaryGroup::Rank => "Rank",
+            SummaryGroup::FadoType => "FADO Type",
+            SummaryGroup::BoardDisposition => "Board Disposition",
+            SummaryGroup::NypdDisposition => "NYPD Disposition",
+        }
+    }
+
+    /// The name the summary query gives the property's column.
+    pub fn column(&self) -> &'static str {
+        match self {
+            SummaryGroup::Command => summary_columns::COMMAND,
+            SummaryGroup::Rank => summary_columns::RANK,
+            SummaryGroup::FadoType => summary_columns::FADO_TYPE,
+            SummaryGroup::BoardDisposition => summary_columns::BOARD_DISPOSITION,
+            SummaryGroup::NypdDisposition => summary_columns::NYPD_DISPOSITION,
+        }
+    }
+}
+
+/// The number of allegations with one combination of the grouped values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    /// The grouped values, in the order they were grouped by.
+    pub groups: Vec<String>,
+    pub allegations: u64,
+    /// The database the allegations were found in.
+    pub status: Database,
+}
+
+impl Summary {
+    /// Read a row of the `status` database's summary, grouped by `group_by`.
+    pub fn from_row(status: Database, group_by: &[SummaryGroup], mut row: Row) -> Result<Self, DeserializeError> {
+        let groups = group_by
+            .iter()
+            .map(|group| row.take(group.column()))
+            .collect::<Result<_, _>>()?;
+
+        let allegations = row.take(summary_columns::ALLEGATIONS)?;
+        let allegations = allegations.parse()
+            .map_err(|_| DeserializeError::InvalidValue { column: "allegations", value: allegations })?;
+
+        Ok(Summary { groups, allegations, status })
+    }
+}
+
+/// Define an enum of well-known strings, with a fallback for anything else.
+/// It converts to and from `String`, and serializes as one, so unknown
+/// values round trip untouched.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $( $(#[$vmeta:meta])* $variant:ident => $value:expr, )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $( $(#[$vmeta])* $variant, )*
+            Other(String),
+        }
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $( $name::$variant => $value, )*
+                    $name::Other(s) => s,
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(s: String) -> Self {
+                match s.as_str() {
+                    $( $value => $name::$variant, )*
+                    _ => $name::Other(s),
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                match value {
+                    $name::Other(s) => s,
+                    value => value.as_str().into(),
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl crate::query::IntoLiteral for $name {
+            fn stringify(&self) -> String {
+                self.as_str().stringify()
+            }
+        }
+    };
+}
+
+string_enum! {
+    /// The FADO (Force, Abuse of Authority, Discourtesy, Offensive
+    /// Language) category of an allegation.
+    pub enum FadoType {
+        Force => "Force",
+        AbuseOfAuthority => "Abuse of Authority",
+        Discourtesy => "Discourtesy",
+        OffensiveLanguage => "Offensive Language",
+        UntruthfulStatement => "Untruthful Statement",
+    }
+}
+
+string_enum! {
+    /// The discipline the NYPD imposed.
+    pub enum NypdDisposition {
+        Charges => "Charges",
+        CommandDisciplineA => "Command Discipline A",
+        CommandDisciplineB => "Command Discipline B",
+        FormalizedTraining => "Formalized Training",
+        Instructions => "Instructions",
+        NoPenalty => "No penalty",
+    }
+}
+
+/// The CCRB board's finding on an allegation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum BoardDisposition {
+    /// Substantiated, with the board's recommended discipline if given,
+    /// e.g. "Substantiated (Command Discipline A)".
+    Substantiated(Option<String>),
+    Unsubstantiated,
+    Exonerated,
+    Unfounded,
+    Other(String),
+}
+
+impl BoardDisposition {
+    pub fn is_substantiated(&self) -> bool {
+        matches!(self, BoardDisposition::Substantiated(_))
+    }
+}
+
+impl From<String> for BoardDisposition {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "Substantiated" => BoardDisposition::Substantiated(None),
+            "Unsubstantiated" => BoardDisposition::Unsubstantiated,
+            "Exonerated" => BoardDisposition::Exonerated,
+            "Unfounded" => BoardDisposition::Unfounded,
+            _ => match s.strip_prefix("Substantiated (").and_then(|rest| rest.strip_suffix(')')) {
+                Some(recommendation) => BoardDisposition::Substantiated(Some(recommendation.into())),
+                None => BoardDisposition::Other(s),
+            },
+        }
+    }
+}
+
+impl crate::query::IntoLiteral for BoardDisposition {
+    fn stringify(&self) -> String {
+        self.to_string().stringify()
+    }
+}
+
+impl From<BoardDisposition> for String {
+    fn from(value: BoardDisposition) -> Self {
+        value.to_string()
+    }
+}
+
+impl std::fmt::Display for BoardDisposition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BoardDisposition::Substantiated(None) => write!(f, "Substantiated"),
+            BoardDisposition::Substantiated(Some(recommendation)) => write!(f, "Substantiated ({})", recommendation),
+            BoardDisposition::Unsubstantiated => write!(f, "Unsubstantiated"),
+            BoardDisposition::Exonerated => write!(f, "Exonerated"),
+            BoardDisposition::Unfounded => write!(f, "Unfounded"),
+            BoardDisposition::Other(s) => write!(f, "{}", s),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum DeserializeError {
-    NotEnoughColumns,
-    TooManyColumns,
+    /// The response has no column by this name.
+    MissingColumn(&'static str),
+    InvalidValue {
+        column: &'static str,
+        value: String,
+    },
 }
 
 impl std::fmt::Display for DeserializeError {
@@ -87,3 +380,104 @@ impl std::fmt::Display for DeserializeError {
 
 impl std::error::Error for DeserializeError {
 }
+
+/// An officer of the active database, for tests.
+#[cfg(test)]
+impl Officer {
+    pub(crate) fn example() -> Self {
+        Officer {
+            id: "12345".into(),
+            command: "001 PD".into(),
+            last_name: "Doe".into(),
+            first_name: "Jane".into(),
+            rank: "Police Officer".into(),
+            shield_no: "98765".into(),
+            status: Database::Active,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(values: &[&str]) -> Result<Details, DeserializeError> {
+        let columns = [
+            details_columns::INDEX,
+            details_columns::COMPLAINT_ID,
+            details_columns::INCIDENT_DATE,
+            details_columns::FADO_TYPE,
+            details_columns::ALLEGATION,
+            details_columns::BOARD_DISPOSITION,
+            details_columns::NYPD_DISPOSITION,
+            details_columns::PENALTY,
+        ].iter().map(|c| c.to_string()).collect::<Vec<_>>();
+
+        let officer = Officer {
+            status: Database::Inactive,
+            ..Officer::example()
+        };
+
+        Details::from_row(&officer, Row::new(&columns, values.iter().map(|s| s.to_string()).collect()))
+    }
+
+    #[test]
+    fn details_csv_round_trip() {
+        let rows = vec![
+            vec!["1", "201803458", "2018-04-03", "Abuse of Authority", "Frisk", "Substantiated (Command Discipline A)", "", ""],
+            vec!["2", "201803458", "", "Discourtesy", "Word", "Complaint Withdrawn", "Formalized Training", "Formalized Training"],
+        ];
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        for row in &rows {
+            let details = parse(row).expect("details");
+            writer.serialize(details).expect("serialize");
+        }
+
+        let csv = String::from_utf8(writer.into_inner().expect("flush")).expect("utf8");
+        let mut lines = csv.lines();
+
+        assert_eq!(lines.next(), Some("officer_id,index,complaint_id,incident_date,fado_type,allegation,board_disposition,nypd_disposition,penalty,status"));
+        for row in &rows {
+            assert_eq!(lines.next(), Some(format!("12345,{},Inactive", row.join(",")).as_str()));
+        }
+    }
+
+    #[test]
+    fn typed_details() {
+        let row = ["7", "201803458", "2018-04-03", "Force", "Pepper spray", "Substantiated (Charges)", "No penalty", ""];
+
+        let details = parse(&row).expect("details");
+
+        assert_eq!(details.officer_id, "12345");
+        assert_eq!(details.index, 7);
+        assert_eq!(details.incident_date, NaiveDate::from_ymd_opt(2018, 4, 3));
+        assert_eq!(details.fado_type, FadoType::Force);
+        assert!(details.board_disposition.is_substantiated());
+        assert_eq!(details.nypd_disposition, Some(NypdDisposition::NoPenalty));
+
+        let bad = ["x", "201803458", "", "Force", "", "", "", ""];
+        assert!(parse(&bad).is_err());
+    }
+
+    #[test]
+    fn columns_by_name() {
+        let columns = vec![
+            officer_columns::SHIELD_NO.to_string(),
+            officer_columns::RANK.to_string(),
+            officer_columns::ID.to_string(),
+            officer_columns::COMMAND.to_string(),
+            officer_columns::FIRST_NAME.to_string(),
+            officer_columns::LAST_NAME.to_string(),
+        ];
+        let values = ["98765", "Police Officer", "12345", "001 PD", "Jane", "Doe"];
+
+        let officer = Officer::from_row(Database::Active, Row::new(&columns, values.iter().map(|s| s.to_string()).collect())).expect("officer");
+
+        assert_eq!((officer.id.as_str(), officer.rank.as_str(), officer.first_name.as_str()), ("12345", "Police Officer", "Jane"));
+
+        let err = Officer::from_row(Database::Active, Row::new(&columns[1..], values[1..].iter().map(|s| s.to_string()).collect())).expect_err("missing");
+
+        assert!(matches!(err, DeserializeError::MissingColumn(officer_columns::SHIELD_NO)));
+    }
+}
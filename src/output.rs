@@ -0,0 +1,373 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::model;
+
+/// Somewhere to write exported officers and their allegations.
+pub trait Sink {
+    /// Write one officer along with all of their allegations.
+    fn write(&mut self, officer: &model::Officer, details: &[model::Details]) -> Result<(), Error>;
+
+    /// Make sure everything written so far has reached the output.
+    fn flush(&mut self) -> Result<(), Error>;
+}
+
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    fn write(&mut self, officer: &model::Officer, details: &[model::Details]) -> Result<(), Error> {
+        (**self).write(officer, details)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        (**self).flush()
+    }
+}
+
+/// Officers and allegations as two CSV tables, joined by officer id.
+pub struct Csv<W: Write> {
+    officers: csv::Writer<W>,
+    details: csv::Writer<W>,
+}
+
+impl<W: Write> Csv<W> {
+    pub fn new(officers: csv::Writer<W>, details: csv::Writer<W>) -> Self {
+        Csv { officers, details }
+    }
+}
+
+impl<W: Write> Sink for Csv<W> {
+    fn write(&mut self, officer: &model::Officer, details: &[model::Details]) -> Result<(), Error> {
+        self.officers.serialize(officer)?;
+
+        for details in details {
+            self.details.serialize(details)?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.officers.flush()?;
+        self.details.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Officers as JSON, one per line.
+///
+/// Allegations are either nested in each officer as an `allegations`
+/// array, or written one per line to a separate output.
+pub struct JsonLines<W: Write> {
+    officers: W,
+    details: Option<W>,
+}
+
+#[derive(Serialize)]
+struct NestedOfficer<'a> {
+    #[serde(flatten)]
+    officer: &'a model::Officer,
+    allegations: &'a [model::Details],
+}
+
+impl<W: Write> JsonLines<W> {
+    /// Write each officer with their allegations nested.
+    pub fn nested(officers: W) -> Self {
+        JsonLines { officers, details: None }
+    }
+
+    /// Write officers and allegations to separate outputs.
+    pub fn flat(officers: W, details: W) -> Self {
+        JsonLines { officers, details: Some(details) }
+    }
+}
+
+impl<W: Write> Sink for JsonLines<W> {
+    fn write(&mut self, officer: &model::Officer, details: &[model::Details]) -> Result<(), Error> {
+        match &mut self.details {
+            None => {
+                serde_json::to_writer(&mut self.officers, &NestedOfficer { officer, allegations: details })
+                    .map_err(Error::JsonLines)?;
+                self.officers.write_all(b"\n")?;
+            }
+            Some(out) => {
+                serde_json::to_writer(&mut self.officers, officer)
+                    .map_err(Error::JsonLines)?;
+                self.officers.write_all(b"\n")?;
+
+                for details in details {
+                    serde_json::to_writer(&mut *out, details)
+                        .map_err(Error::JsonLines)?;
+                    out.write_all(b"\n")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.officers.flush()?;
+
+        if let Some(out) = &mut self.details {
+            out.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Officers, complaints and allegations as normalized SQLite tables.
+///
+/// Rows are upserted, so writing the same officer twice, e.g. when
+/// resuming an export, leaves a single copy.  Writes are batched into a
+/// transaction that is committed on `flush`.
+pub struct Sqlite {
+    conn: rusqlite::Connection,
+    in_transaction: bool,
+}
+
+const SQLITE_SCHEMA: &str = "
+    PRAGMA foreign_keys = ON;
+
+    CREATE TABLE IF NOT EXISTS officers (
+        id TEXT NOT NULL,
+        status TEXT NOT NULL,
+        command TEXT NOT NULL,
+        last_name TEXT NOT NULL,
+        first_name TEXT NOT NULL,
+        rank TEXT NOT NULL,
+        shield_no TEXT NOT NULL,
+        PRIMARY KEY (id, status)
+    );
+    CREATE INDEX IF NOT EXISTS officers_shield_no ON officers (shield_no);
+    CREATE INDEX IF NOT EXISTS officers_command ON officers (command);
+
+    CREATE TABLE IF NOT EXISTS complaints (
+        id TEXT PRIMARY KEY,
+        incident_date TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS allegations (
+        officer_id TEXT NOT NULL,
+        status TEXT NOT NULL,
+        \"index\" INTEGER NOT NULL,
+        complaint_id TEXT NOT NULL REFERENCES complaints (id),
+        fado_type TEXT NOT NULL,
+        allegation TEXT NOT NULL,
+        board_disposition TEXT NOT NULL,
+        nypd_disposition TEXT NOT NULL,
+        penalty TEXT NOT NULL,
+        PRIMARY KEY (officer_id, status, \"index\"),
+        FOREIGN KEY (officer_id, status) REFERENCES officers (id, status)
+    );
+    CREATE INDEX IF NOT EXISTS allegations_complaint_id ON allegations (complaint_id);
+
+    CREATE VIEW IF NOT EXISTS officer_allegations AS
+        SELECT
+            officers.*,
+            COUNT(allegations.officer_id) AS allegations,
+            COUNT(DISTINCT allegations.complaint_id) AS complaints,
+            COALESCE(SUM(allegations.board_disposition LIKE 'Substantiated%'), 0) AS substantiated
+        FROM officers
+        LEFT JOIN allegations ON allegations.officer_id = officers.id AND allegations.status = officers.status
+        GROUP BY officers.id, officers.status;
+
+    CREATE VIEW IF NOT EXISTS command_allegations AS
+        SELECT
+            command,
+            COUNT(*) AS officers,
+            SUM(allegations) AS allegations,
+            SUM(substantiated) AS substantiated
+        FROM officer_allegations
+        GROUP BY command;
+
+    CREATE VIEW IF NOT EXISTS officers_in_both AS
+        SELECT id
+        FROM officers
+        GROUP BY id
+        HAVING COUNT(DISTINCT status) > 1;
+";
+
+impl Sqlite {
+    /// Open (or create) the database file at `path`.
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        Sqlite::new(rusqlite::Connection::open(path)?)
+    }
+
+    /// Write to an open connection, creating the tables if need be.
+    pub fn new(conn: rusqlite::Connection) -> Result<Self, Error> {
+        conn.execute_batch(SQLITE_SCHEMA)?;
+
+        Ok(Sqlite { conn, in_transaction: false })
+    }
+
+    /// The underlying connection.
+    pub fn connection(&self) -> &rusqlite::Connection {
+        &self.conn
+    }
+}
+
+impl Sink for Sqlite {
+    fn write(&mut self, officer: &model::Officer, details: &[model::Details]) -> Result<(), Error> {
+        if !self.in_transaction {
+            self.conn.execute_batch("BEGIN")?;
+            self.in_transaction = true;
+        }
+
+        self.conn
+            .prepare_cached("
+                INSERT INTO officers (id, status, command, last_name, first_name, rank, shield_no)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                ON CONFLICT (id, status) DO UPDATE SET
+                    command = excluded.command,
+                    last_name = excluded.last_name,
+                    first_name = excluded.first_name,
+                    rank = excluded.rank,
+                    shield_no = excluded.shield_no
+            ")?
+            .execute(rusqlite::params![
+                officer.id,
+                officer.status.as_str(),
+                officer.command,
+                officer.last_name,
+                officer.first_name,
+                officer.rank,
+                officer.shield_no,
+            ])?;
+
+        for details in details {
+            self.conn
+                .prepare_cached("
+                    INSERT INTO complaints (id, incident_date)
+                    VALUES (?1, ?2)
+                    ON CONFLICT (id) DO NOTHING
+                ")?
+                .execute(rusqlite::params![details.complaint_id, details.incident_date.map(|date| date.to_string())])?;
+
+            self.conn
+                .prepare_cached("
+                    INSERT INTO allegations (officer_id, status, \"index\", complaint_id, fado_type, allegation, board_disposition, nypd_disposition, penalty)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                    ON CONFLICT (officer_id, status, \"index\") DO UPDATE SET
+                        complaint_id = excluded.complaint_id,
+                        fado_type = excluded.fado_type,
+                        allegation = excluded.allegation,
+                        board_disposition = excluded.board_disposition,
+                        nypd_disposition = excluded.nypd_disposition,
+                        penalty = excluded.penalty
+                ")?
+                .execute(rusqlite::params![
+                    details.officer_id,
+                    details.status.as_str(),
+                    details.index,
+                    details.complaint_id,
+                    details.fado_type.as_str(),
+                    details.allegation,
+                    details.board_disposition.to_string(),
+                    details.nypd_disposition.as_ref().map(|d| d.as_str()).unwrap_or_default(),
+                    details.penalty,
+                ])?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        if self.in_transaction {
+            self.conn.execute_batch("COMMIT")?;
+            self.in_transaction = false;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn details() -> model::Details {
+        model::Details {
+            officer_id: "12345".into(),
+            index: 1,
+            complaint_id: "201803458".into(),
+            incident_date: chrono::NaiveDate::from_ymd_opt(2018, 4, 3),
+            fado_type: model::FadoType::AbuseOfAuthority,
+            allegation: "Frisk".into(),
+            board_disposition: model::BoardDisposition::Unsubstantiated,
+            nypd_disposition: None,
+            penalty: "".into(),
+            status: crate::Database::Active,
+        }
+    }
+
+    #[test]
+    fn json_lines_nested() {
+        let mut sink = JsonLines::nested(vec![]);
+
+        sink.write(&model::Officer::example(), &[details()]).expect("write");
+        sink.write(&model::Officer::example(), &[]).expect("write");
+
+        let out = String::from_utf8(sink.officers).expect("utf8");
+        let lines = out.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "{\"id\":\"12345\",\"command\":\"001 PD\",\"last_name\":\"Doe\",\"first_name\":\"Jane\",\"rank\":\"Police Officer\",\"shield_no\":\"98765\",\"status\":\"Active\",\"allegations\":[{\"officer_id\":\"12345\",\"index\":1,\"complaint_id\":\"201803458\",\"incident_date\":\"2018-04-03\",\"fado_type\":\"Abuse of Authority\",\"allegation\":\"Frisk\",\"board_disposition\":\"Unsubstantiated\",\"nypd_disposition\":null,\"penalty\":\"\",\"status\":\"Active\"}]}");
+        assert!(lines[1].ends_with(",\"allegations\":[]}"));
+    }
+
+    #[test]
+    fn json_lines_flat() {
+        let mut sink = JsonLines::flat(vec![], vec![]);
+
+        sink.write(&model::Officer::example(), &[details(), details()]).expect("write");
+
+        let officers = String::from_utf8(sink.officers).expect("utf8");
+        let details = String::from_utf8(sink.details.expect("details")).expect("utf8");
+
+        assert_eq!(officers.lines().count(), 1);
+        assert!(!officers.contains("allegations"));
+        assert_eq!(details.lines().count(), 2);
+    }
+
+    #[test]
+    fn sqlite_upserts() {
+        let mut sink = Sqlite::new(rusqlite::Connection::open_in_memory().expect("open")).expect("schema");
+
+        let mut substantiated = details();
+        substantiated.index = 2;
+        substantiated.board_disposition = model::BoardDisposition::Substantiated(Some("Command Discipline A".into()));
+
+        sink.write(&model::Officer::example(), &[details(), substantiated]).expect("write");
+        sink.flush().expect("flush");
+        sink.write(&model::Officer::example(), &[details()]).expect("write again");
+        sink.flush().expect("flush");
+
+        let conn = sink.connection();
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).expect("count")
+        };
+
+        assert_eq!(count("officers"), 1);
+        assert_eq!(count("complaints"), 1);
+        assert_eq!(count("allegations"), 2);
+
+        let (allegations, substantiated): (i64, i64) = conn
+            .query_row("SELECT allegations, substantiated FROM officer_allegations WHERE id = '12345'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .expect("view");
+
+        assert_eq!((allegations, substantiated), (2, 1));
+
+        let mut inactive = model::Officer::example();
+        inactive.status = crate::Database::Inactive;
+
+        sink.write(&inactive, &[]).expect("write inactive");
+        sink.flush().expect("flush");
+
+        let conn = sink.connection();
+        assert_eq!(conn.query_row("SELECT COUNT(*) FROM officers", [], |row| row.get::<_, i64>(0)).expect("count"), 2);
+        assert_eq!(conn.query_row("SELECT id FROM officers_in_both", [], |row| row.get::<_, String>(0)).expect("in both"), "12345");
+    }
+}
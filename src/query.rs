@@ -1,6 +1,8 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::model;
+use crate::report::Report;
+use crate::model::{details_columns, officer_columns, summary_columns};
 
 #[derive(Serialize, Debug)]
 #[serde(rename_all="camelCase")]
@@ -50,13 +52,13 @@ struct RequestCommand {
 #[derive(Serialize, Debug)]
 #[serde(rename_all="PascalCase")]
 struct QueryWrapper {
-    query: Query,
+    query: SemanticQuery,
     binding: Binding,
 }
 
 #[derive(Serialize, Debug)]
 #[serde(rename_all="PascalCase")]
-struct Query {
+struct SemanticQuery {
     version: usize,
     from: Vec<FromClause>,
     select: Vec<SelectClause>,
@@ -68,20 +70,52 @@ struct Query {
 #[derive(Serialize, Debug)]
 #[serde(rename_all="PascalCase")]
 struct ConditionWrapper {
-    condition: Condition,
+    condition: ConditionExpression,
 }
 
 #[derive(Serialize, Debug)]
 #[serde(rename_all="PascalCase")]
-enum Condition {
+enum ConditionExpression {
+    #[serde(rename_all="PascalCase")]
+    And {
+        left: Box<ConditionExpression>,
+        right: Box<ConditionExpression>,
+    },
+    #[serde(rename_all="PascalCase")]
+    Or {
+        left: Box<ConditionExpression>,
+        right: Box<ConditionExpression>,
+    },
+    #[serde(rename_all="PascalCase")]
+    Not {
+        expression: Box<ConditionExpression>,
+    },
+    #[serde(rename_all="PascalCase")]
+    Comparison {
+        comparison_kind: ComparisonKind,
+        left: OrderByExpression,
+        right: LiteralWrapper,
+    },
     #[serde(rename_all="PascalCase")]
     In {
         expressions: Vec<OrderByExpression>,
         values: Vec<Vec<LiteralWrapper>>,
     },
     #[serde(rename_all="PascalCase")]
-    Not {
-        expression: ComparisonWrapper,
+    Between {
+        expression: OrderByExpression,
+        lower_bound: LiteralWrapper,
+        upper_bound: LiteralWrapper,
+    },
+    #[serde(rename_all="PascalCase")]
+    Contains {
+        left: OrderByExpression,
+        right: LiteralWrapper,
+    },
+    #[serde(rename_all="PascalCase")]
+    StartsWith {
+        left: OrderByExpression,
+        right: LiteralWrapper,
     },
 }
 
@@ -91,50 +125,120 @@ struct LiteralWrapper {
     literal: Literal,
 }
 
-#[derive(Serialize, Debug)]
+/// A value as written in a query, e.g. `'text'`, `123L` or `null`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all="PascalCase")]
-struct Literal {
+pub struct Literal {
     value: String,
 }
 
+impl Literal {
+    pub fn new<T: IntoLiteral + ?Sized>(value: &T) -> Self {
+        Literal { value: value.stringify() }
+    }
+
+    pub fn null() -> Self {
+        Literal::raw("null")
+    }
+
+    /// A literal written out exactly as given.
+    pub fn raw<S: Into<String>>(value: S) -> Self {
+        Literal { value: value.into() }
+    }
+
+    /// The literal as PowerBI writes it.
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+}
+
+/// A value that can be written as a query literal.
 pub trait IntoLiteral {
     fn stringify(&self) -> String;
 }
 
-impl LiteralWrapper {
-    fn new<T: IntoLiteral>(value: &T) -> LiteralWrapper {
-        LiteralWrapper {
-            literal: Literal {
-                value: value.stringify(),
-            },
-        }
+impl From<Literal> for LiteralWrapper {
+    fn from(literal: Literal) -> Self {
+        LiteralWrapper { literal }
+    }
+}
+
+/// Text is quoted, with embedded quotes doubled: `'O''Brien'`.
+impl IntoLiteral for str {
+    fn stringify(&self) -> String {
+        format!("'{}'", self.replace('\'', "''"))
     }
 }
 
-impl<S: ToString> IntoLiteral for S {
+impl IntoLiteral for String {
     fn stringify(&self) -> String {
-        let mut result = "'".to_string();
+        self.as_str().stringify()
+    }
+}
 
-        result.push_str(&self.to_string().replace('\'', "\\'"));
+impl<T: IntoLiteral + ?Sized> IntoLiteral for &T {
+    fn stringify(&self) -> String {
+        (**self).stringify()
+    }
+}
 
-        result.push_str("'");
+/// `None` is `null`.
+impl<T: IntoLiteral> IntoLiteral for Option<T> {
+    fn stringify(&self) -> String {
+        match self {
+            Some(value) => value.stringify(),
+            None => "null".into(),
+        }
+    }
+}
 
-        result
+impl IntoLiteral for bool {
+    fn stringify(&self) -> String {
+        self.to_string()
     }
 }
 
-#[derive(Serialize, Debug)]
-#[serde(rename_all="PascalCase")]
-struct ComparisonWrapper {
-    comparison: Comparison,
+macro_rules! integer_literal {
+    ($($t:ty),*) => {
+        $(
+            /// Integers have an `L` suffix: `123L`.
+            impl IntoLiteral for $t {
+                fn stringify(&self) -> String {
+                    format!("{}L", self)
+                }
+            }
+        )*
+    };
 }
 
-#[derive(Serialize, Debug)]
-#[serde(rename_all="PascalCase")]
-struct Comparison {
-    comparison_kind: usize,
-    left: OrderByExpression,
-    right: LiteralWrapper,
+integer_literal!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! double_literal {
+    ($($t:ty),*) => {
+        $(
+            /// Doubles have a `D` suffix: `1.5D`.
+            impl IntoLiteral for $t {
+                fn stringify(&self) -> String {
+                    format!("{}D", self)
+                }
+            }
+        )*
+    };
+}
+
+double_literal!(f32, f64);
+
+/// Dates are written as midnight: `datetime'2019-01-01T00:00:00'`.
+impl IntoLiteral for chrono::NaiveDate {
+    fn stringify(&self) -> String {
+        self.and_hms_opt(0, 0, 0).expect("midnight").stringify()
+    }
+}
+
+impl IntoLiteral for chrono::NaiveDateTime {
+    fn stringify(&self) -> String {
+        format!("datetime'{}'", self.format("%Y-%m-%dT%H:%M:%S"))
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -149,10 +253,22 @@ struct FromClause {
 #[derive(Serialize, Debug)]
 #[serde(rename_all="PascalCase")]
 struct SelectClause {
-    column: SelectColumn,
+    #[serde(flatten)]
+    expression: SelectItem,
     name: String,
 }
 
+#[derive(Serialize, Debug)]
+#[serde(rename_all="PascalCase")]
+enum SelectItem {
+    Column(SelectColumn),
+    #[serde(rename_all="PascalCase")]
+    Aggregation {
+        expression: OrderByExpression,
+        function: Aggregate,
+    },
+}
+
 #[derive(Serialize, Debug)]
 #[serde(rename_all="PascalCase")]
 struct SelectColumn {
@@ -160,6 +276,19 @@ struct SelectColumn {
     property: String,
 }
 
+impl SelectColumn {
+    fn new<P: Into<String>>(source: &str, property: P) -> Self {
+        SelectColumn {
+            expression: SelectExpression {
+                source_ref: SourceRef {
+                    source: source.into(),
+                },
+            },
+            property: property.into(),
+        }
+    }
+}
+
 #[derive(Serialize, Debug)]
 #[serde(rename_all="PascalCase")]
 struct SelectExpression {
@@ -226,476 +355,593 @@ struct DataReductionWindow {
     restart_tokens: Option<Vec<Vec<String>>>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Database {
     Active,
     Inactive,
 }
 
 impl Database {
-    fn get_model_id(&self) -> usize {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Database::Active => 404287,
-            Database::Inactive => 404284,
+            Database::Active => "Active",
+            Database::Inactive => "Inactive",
         }
     }
+}
 
-    pub fn get_bi_resource_key(&self) -> &'static str {
+/// The number of officers fetched per page of the index.
+pub const DEFAULT_WINDOW: usize = 500;
+
+/// Which way to sort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ascending = 1,
+    Descending = 2,
+}
+
+/// How a comparison condition compares a property with a literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonKind {
+    Equal = 0,
+    GreaterThan = 1,
+    GreaterThanOrEqual = 2,
+    LessThan = 3,
+    LessThanOrEqual = 4,
+}
+
+impl Serialize for ComparisonKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+/// A function summarizing a property's values in each group of rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum = 0,
+    /// The number of distinct values of the property, not of rows.
+    Count = 2,
+    Min = 3,
+    Max = 4,
+    /// The number of rows where the property isn't null.  This is the
+    /// service's plain count; count a property that's never null to count
+    /// rows.
+    CountNonNull = 5,
+}
+
+impl Aggregate {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Database::Active => BI_RESOURCE_KEY_VALUE_ACTIVE,
-            Database::Inactive => BI_RESOURCE_KEY_VALUE_INACTIVE,
+            Aggregate::Sum => "Sum",
+            Aggregate::Count => "Count",
+            Aggregate::Min => "Min",
+            Aggregate::Max => "Max",
+            Aggregate::CountNonNull => "CountNonNull",
         }
     }
 }
 
-const BI_RESOURCE_KEY_VALUE_ACTIVE: &str = "b2c8d2f2-3ad1-48dc-883c-d4163a6e2d8f";
-const BI_RESOURCE_KEY_VALUE_INACTIVE: &str = "87914378-578f-4f43-b75e-8ddaeafbdda2";
-
-pub fn get_index(db: &Database, restart_tokens: Option<Vec<String>>) -> Request {
-    Request {
-        version: "1.0.0",
-        queries: vec![
-            RequestQueryWrapper {
-                query: RequestQuery {
-                    commands: vec![
-                        RequestCommand {
-                            semantic_query_data_shape_command: QueryWrapper {
-                                query: Query {
-                                    version: 2,
-                                    from: vec![
-                                        FromClause {
-                                            name: "q1".into(),
-                                            entity: "CCRB Active - Oracle".into(),
-                                            from_type: 0,
-                                        },
-                                    ],
-                                    select: vec![
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Unique Id".into(),
-                                            },
-                                            name: "Query1.Unique Id".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Command".into(),
-                                            },
-                                            name: "Query1.Command1".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Last Name".into(),
-                                            },
-                                            name: "Query1.Last Name1".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "First Name".into(),
-                                            },
-                                            name: "Query1.First Name1".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Rank".into(),
-                                            },
-                                            name: "Query1.Rank1".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Shield No".into(),
-                                            },
-                                            name: "Query1.ShieldNo".into(),
-                                        },
-                                    ],
-                                    where_clause: None,
-                                    order_by: vec![
-                                        OrderByClause {
-                                            direction: 1,
-                                            expression: OrderByExpression {
-                                                column: SelectColumn {
-                                                    expression: SelectExpression {
-                                                        source_ref: SourceRef {
-                                                            source: "q1".into(),
-                                                        },
-                                                    },
-                                                    property: "Command".into(),
-                                                },
-                                            },
-                                        },
-                                    ],
-                                },
-                                binding: Binding {
-                                    primary: Primary {
-                                        groupings: vec![
-                                            Grouping {
-                                                projections: vec![0, 1, 2, 3, 4, 5],
-                                            }
-                                        ],
-                                    },
-                                    data_reduction: DataReduction {
-                                        data_volume: 3,
-                                        primary: DataReductionPrimary {
-                                            window: DataReductionWindow {
-                                                count: 500,
-                                                restart_tokens: restart_tokens.map(|v| vec![v]),
-                                            },
-                                        },
-                                    },
-                                    version: 1,
-                                },
-                            },
-                        },
-                    ],
+impl Serialize for Aggregate {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+/// A filter on the rows of a query, in terms of the queried entity's
+/// properties.
+///
+/// Conditions combine with [`and`](Condition::and), [`or`](Condition::or)
+/// and `!`:
+///
+/// ```
+/// # use ccrb_export::query::Condition;
+/// # use chrono::NaiveDate;
+/// let since_2019 = NaiveDate::from_ymd_opt(2019, 1, 1).unwrap();
+/// let until_2020 = NaiveDate::from_ymd_opt(2020, 12, 31).unwrap();
+///
+/// let condition = Condition::between("Incident Date", &since_2019, &until_2020)
+///     .and(Condition::equal("FADO Type", "Force"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    Comparison {
+        property: String,
+        kind: ComparisonKind,
+        value: Literal,
+    },
+    /// The property is one of the values.
+    In {
+        property: String,
+        values: Vec<Literal>,
+    },
+    /// The property is between the bounds, inclusive.
+    Between {
+        property: String,
+        lower: Literal,
+        upper: Literal,
+    },
+    /// The property contains the text.
+    Contains {
+        property: String,
+        value: Literal,
+    },
+    /// The property starts with the text.
+    StartsWith {
+        property: String,
+        value: Literal,
+    },
+    IsNull {
+        property: String,
+    },
+}
+
+impl Condition {
+    pub fn compare<T: IntoLiteral + ?Sized>(property: &str, kind: ComparisonKind, value: &T) -> Self {
+        Condition::Comparison {
+            property: property.into(),
+            kind,
+            value: Literal::new(value),
+        }
+    }
+
+    pub fn equal<T: IntoLiteral + ?Sized>(property: &str, value: &T) -> Self {
+        Condition::compare(property, ComparisonKind::Equal, value)
+    }
+
+    pub fn is_in<I, T>(property: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: IntoLiteral,
+    {
+        Condition::In {
+            property: property.into(),
+            values: values.into_iter().map(|value| Literal::new(&value)).collect(),
+        }
+    }
+
+    pub fn between<T: IntoLiteral + ?Sized>(property: &str, lower: &T, upper: &T) -> Self {
+        Condition::Between {
+            property: property.into(),
+            lower: Literal::new(lower),
+            upper: Literal::new(upper),
+        }
+    }
+
+    pub fn contains<T: IntoLiteral + ?Sized>(property: &str, value: &T) -> Self {
+        Condition::Contains {
+            property: property.into(),
+            value: Literal::new(value),
+        }
+    }
+
+    pub fn starts_with<T: IntoLiteral + ?Sized>(property: &str, value: &T) -> Self {
+        Condition::StartsWith {
+            property: property.into(),
+            value: Literal::new(value),
+        }
+    }
+
+    pub fn is_null(property: &str) -> Self {
+        Condition::IsNull { property: property.into() }
+    }
+
+    /// Both this condition and `other` hold.
+    pub fn and(self, other: Condition) -> Self {
+        Condition::And(Box::new(self), Box::new(other))
+    }
+
+    /// Either this condition or `other` holds.
+    pub fn or(self, other: Condition) -> Self {
+        Condition::Or(Box::new(self), Box::new(other))
+    }
+
+    /// The condition as sent, with properties of the entity named `source`.
+    fn expression(self, source: &str) -> ConditionExpression {
+        let column = |property: String| OrderByExpression { column: SelectColumn::new(source, property) };
+        let boxed = |condition: Condition| Box::new(condition.expression(source));
+
+        match self {
+            Condition::And(left, right) => ConditionExpression::And { left: boxed(*left), right: boxed(*right) },
+            Condition::Or(left, right) => ConditionExpression::Or { left: boxed(*left), right: boxed(*right) },
+            Condition::Not(condition) => ConditionExpression::Not { expression: boxed(*condition) },
+            Condition::Comparison { property, kind, value } => ConditionExpression::Comparison {
+                comparison_kind: kind,
+                left: column(property),
+                right: value.into(),
+            },
+            Condition::In { property, values } => ConditionExpression::In {
+                expressions: vec![column(property)],
+                values: values.into_iter().map(|value| vec![value.into()]).collect(),
+            },
+            Condition::Between { property, lower, upper } => ConditionExpression::Between {
+                expression: column(property),
+                lower_bound: lower.into(),
+                upper_bound: upper.into(),
+            },
+            Condition::Contains { property, value } => ConditionExpression::Contains {
+                left: column(property),
+                right: value.into(),
+            },
+            Condition::StartsWith { property, value } => ConditionExpression::StartsWith {
+                left: column(property),
+                right: value.into(),
+            },
+            // PowerBI has no null test of its own; it compares with null.
+            Condition::IsNull { property } => ConditionExpression::Comparison {
+                comparison_kind: ComparisonKind::Equal,
+                left: column(property),
+                right: Literal::null().into(),
+            },
+        }
+    }
+}
+
+/// The condition doesn't hold.
+impl std::ops::Not for Condition {
+    type Output = Condition;
+
+    fn not(self) -> Condition {
+        Condition::Not(Box::new(self))
+    }
+}
+
+/// A semantic query over one entity, built up a clause at a time.
+///
+/// ```
+/// # use ccrb_export::query::Query;
+/// let request = Query::from("q1", "CCRB Active - Oracle")
+///     .select("Command")
+///     .filter_in("Shield No", ["98765"])
+///     .order_by("Command", ccrb_export::query::Direction::Ascending)
+///     .window(500)
+///     .request(&ccrb_export::Report::active());
+/// ```
+#[derive(Debug)]
+pub struct Query {
+    source: String,
+    entity: String,
+    select: Vec<SelectClause>,
+    where_clause: Vec<ConditionWrapper>,
+    order_by: Vec<OrderByClause>,
+    window: usize,
+    restart_tokens: Option<Vec<String>>,
+    cache_options: Option<usize>,
+}
+
+impl Query {
+    /// Query `entity`, referring to it as `source`.
+    pub fn from<S: Into<String>, E: Into<String>>(source: S, entity: E) -> Self {
+        Query {
+            source: source.into(),
+            entity: entity.into(),
+            select: vec![],
+            where_clause: vec![],
+            order_by: vec![],
+            window: DEFAULT_WINDOW,
+            restart_tokens: None,
+            cache_options: None,
+        }
+    }
+
+    /// Select a property, naming the column `source.property`.
+    pub fn select(self, property: &str) -> Self {
+        let name = format!("{}.{}", self.source, property);
+        self.select_as(property, name)
+    }
+
+    /// Select a property, naming the column `name`.
+    pub fn select_as<N: Into<String>>(mut self, property: &str, name: N) -> Self {
+        self.select.push(SelectClause {
+            expression: SelectItem::Column(self.column(property)),
+            name: name.into(),
+        });
+        self
+    }
+
+    /// Group the rows by a property.  The service groups by every selected
+    /// column, so this is `select` by another name, for queries that
+    /// aggregate.
+    pub fn group_by(self, property: &str) -> Self {
+        self.select(property)
+    }
+
+    /// Select an aggregate of a property over each group, naming the column
+    /// `Function(source.property)`.
+    pub fn aggregate(self, function: Aggregate, property: &str) -> Self {
+        let name = format!("{}({}.{})", function.as_str(), self.source, property);
+        self.aggregate_as(function, property, name)
+    }
+
+    /// Select an aggregate of a property over each group, naming the column
+    /// `name`.
+    pub fn aggregate_as<N: Into<String>>(mut self, function: Aggregate, property: &str, name: N) -> Self {
+        self.select.push(SelectClause {
+            expression: SelectItem::Aggregation {
+                expression: OrderByExpression { column: self.column(property) },
+                function,
+            },
+            name: name.into(),
+        });
+        self
+    }
+
+    /// Only include rows matching `condition`.  The conditions of several
+    /// calls must all hold.
+    pub fn filter(mut self, condition: Condition) -> Self {
+        let condition = condition.expression(&self.source);
+        self.where_clause.push(ConditionWrapper { condition });
+        self
+    }
+
+    /// Only include rows where the property is one of `values`.
+    pub fn filter_in<I, T>(self, property: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: IntoLiteral,
+    {
+        self.filter(Condition::is_in(property, values))
+    }
+
+    /// Sort by a property.  Earlier calls take precedence.
+    pub fn order_by(mut self, property: &str, direction: Direction) -> Self {
+        self.order_by.push(OrderByClause {
+            direction: direction as usize,
+            expression: OrderByExpression { column: self.column(property) },
+        });
+        self
+    }
+
+    /// Return at most `count` rows.
+    pub fn window(mut self, count: usize) -> Self {
+        self.window = count;
+        self
+    }
+
+    /// Continue after the row the restart tokens of a previous page point to.
+    pub fn restart_tokens(mut self, restart_tokens: Option<Vec<String>>) -> Self {
+        self.restart_tokens = restart_tokens;
+        self
+    }
+
+    /// Set the service's caching options for the query.
+    pub fn cache_options(mut self, cache_options: usize) -> Self {
+        self.cache_options = Some(cache_options);
+        self
+    }
+
+    /// The request for this query against `report`'s model.
+    pub fn request(self, report: &Report) -> Request {
+        let projections = (0..self.select.len()).collect();
+
+        let query = SemanticQuery {
+            version: 2,
+            from: vec![
+                FromClause {
+                    name: self.source,
+                    entity: self.entity,
+                    from_type: 0,
                 },
-                cache_options: Some(7),
-                query_id: "",
-                application_context: Some(ApplicationContext {
-                    dataset_id: "523ab509-8e2d-43ed-bfad-11fcd05180d7".into(),
-                    sources: vec![
-                        Source {
-                            report_id: "f508555a-b39d-4c10-8d46-a14bc282e079".into(),
-                        }
-                    ],
-                }),
-            }
-        ],
-        cancel_queries: vec![],
-        model_id: db.get_model_id(),
-    }
-}
-
-pub fn get_followup(db: &Database, officer: &model::Officer) -> Request {
-    Request {
-        version: "1.0.0",
-        queries: vec![
-            RequestQueryWrapper {
-                query: RequestQuery {
-                    commands: vec![
-                        RequestCommand {
-                            semantic_query_data_shape_command: QueryWrapper {
-                                query: Query {
-                                    version: 2,
-                                    from: vec![
-                                        FromClause {
-                                            name: "q1".into(),
-                                            entity: "CCRB Active - Oracle".into(),
-                                            from_type: 0,
-                                        },
-                                    ],
-                                    select: vec![
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Rn".into(),
-                                            },
-                                            name: "Sum(Query1.Rn)".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Complaint ID".into(),
-                                            },
-                                            name: "CountNonNull(Query1.Complaint Id)1".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Incident Date".into(),
-                                            },
-                                            name: "Query1.Incident Date".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "FADO Type".into(),
-                                            },
-                                            name: "Query1.FADO Type1".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Allegation".into(),
-                                            },
-                                            name: "Query1.Allegation1".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Board Disposition".into(),
-                                            },
-                                            name: "Query1.Board Disposition1".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "NYPD Disposition".into(),
-                                            },
-                                            name: "Query1.NYPD Disposition".into(),
-                                        },
-                                        SelectClause {
-                                            column: SelectColumn {
-                                                expression: SelectExpression {
-                                                    source_ref: SourceRef {
-                                                        source: "q1".into(),
-                                                    },
-                                                },
-                                                property: "Penalty".into(),
-                                            },
-                                            name: "Query1.PenaltyDesc1".into(),
-                                        },
-                                    ],
-                                    where_clause: Some(vec![
-                                        ConditionWrapper {
-                                            condition: Condition::Not {
-                                                expression: ComparisonWrapper {
-                                                    comparison: Comparison {
-                                                        comparison_kind: 0,
-                                                        left: OrderByExpression {
-                                                            column: SelectColumn {
-                                                                expression: SelectExpression {
-                                                                    source_ref: SourceRef {
-                                                                        source: "q1".into(),
-                                                                    },
-                                                                },
-                                                                property: "Rn".into(),
-                                                            },
-                                                        },
-                                                        right: LiteralWrapper {
-                                                            literal: Literal {
-                                                                value: "0L".into(),
-                                                            },
-                                                        },
-                                                    },
-                                                },
-                                            },
-                                        },
-                                        ConditionWrapper {
-                                            condition: Condition::In {
-                                                expressions: vec![
-                                                    OrderByExpression {
-                                                        column: SelectColumn {
-                                                            expression: SelectExpression {
-                                                                source_ref: SourceRef {
-                                                                    source: "q1".into(),
-                                                                },
-                                                            },
-                                                            property: "Shield No".into(),
-                                                        },
-                                                    },
-                                                ],
-                                                values: vec![vec![
-                                                    LiteralWrapper::new(&officer.shield_no),
-                                                ]],
-                                            }
-                                        },
-                                        ConditionWrapper {
-                                            condition: Condition::In {
-                                                expressions: vec![
-                                                    OrderByExpression {
-                                                        column: SelectColumn {
-                                                            expression: SelectExpression {
-                                                                source_ref: SourceRef {
-                                                                    source: "q1".into(),
-                                                                },
-                                                            },
-                                                            property: "Last Name".into(),
-                                                        },
-                                                    },
-                                                ],
-                                                values: vec![vec![
-                                                    LiteralWrapper::new(&officer.last_name),
-                                                ]],
-                                            }
-                                        },
-                                        ConditionWrapper {
-                                            condition: Condition::In {
-                                                expressions: vec![
-                                                    OrderByExpression {
-                                                        column: SelectColumn {
-                                                            expression: SelectExpression {
-                                                                source_ref: SourceRef {
-                                                                    source: "q1".into(),
-                                                                },
-                                                            },
-                                                            property: "First Name".into(),
-                                                        },
-                                                    },
-                                                ],
-                                                values: vec![vec![
-                                                    LiteralWrapper::new(&officer.first_name),
-                                                ]],
-                                            }
-                                        },
-                                        ConditionWrapper {
-                                            condition: Condition::In {
-                                                expressions: vec![
-                                                    OrderByExpression {
-                                                        column: SelectColumn {
-                                                            expression: SelectExpression {
-                                                                source_ref: SourceRef {
-                                                                    source: "q1".into(),
-                                                                },
-                                                            },
-                                                            property: "Unique Id".into(),
-                                                        },
-                                                    },
-                                                ],
-                                                values: vec![vec![
-                                                    LiteralWrapper::new(&officer.id),
-                                                ]],
-                                            }
-                                        },
-                                        ConditionWrapper {
-                                            condition: Condition::In {
-                                                expressions: vec![
-                                                    OrderByExpression {
-                                                        column: SelectColumn {
-                                                            expression: SelectExpression {
-                                                                source_ref: SourceRef {
-                                                                    source: "q1".into(),
-                                                                },
-                                                            },
-                                                            property: "Command".into(),
-                                                        },
-                                                    },
-                                                ],
-                                                values: vec![vec![
-                                                    LiteralWrapper::new(&officer.command),
-                                                ]],
-                                            }
-                                        },
-                                        ConditionWrapper {
-                                            condition: Condition::In {
-                                                expressions: vec![
-                                                    OrderByExpression {
-                                                        column: SelectColumn {
-                                                            expression: SelectExpression {
-                                                                source_ref: SourceRef {
-                                                                    source: "q1".into(),
-                                                                },
-                                                            },
-                                                            property: "Rank".into(),
-                                                        },
-                                                    },
-                                                ],
-                                                values: vec![vec![
-                                                    LiteralWrapper::new(&officer.rank),
-                                                ]],
-                                            }
-                                        },
-                                    ]),
-                                    order_by: vec![
-                                        OrderByClause {
-                                            direction: 1,
-                                            expression: OrderByExpression {
-                                                column: SelectColumn {
-                                                    expression: SelectExpression {
-                                                        source_ref: SourceRef {
-                                                            source: "q1".into(),
-                                                        },
-                                                    },
-                                                    property: "Rn".into(),
-                                                },
-                                            },
-                                        },
-                                    ],
-                                },
-                                binding: Binding {
-                                    primary: Primary {
-                                        groupings: vec![
-                                            Grouping {
-                                                projections: vec![0, 1, 2, 3, 4, 5, 6, 7],
-                                            }
-                                        ],
-                                    },
-                                    data_reduction: DataReduction {
-                                        data_volume: 3,
-                                        primary: DataReductionPrimary {
-                                            window: DataReductionWindow {
-                                                count: 500,
-                                                restart_tokens: None,
-                                            },
-                                        },
-                                    },
-                                    version: 1,
-                                },
-                            },
-                        },
-                    ],
+            ],
+            select: self.select,
+            where_clause: if self.where_clause.is_empty() { None } else { Some(self.where_clause) },
+            order_by: self.order_by,
+        };
+
+        let binding = Binding {
+            primary: Primary {
+                groupings: vec![Grouping { projections }],
+            },
+            data_reduction: DataReduction {
+                data_volume: 3,
+                primary: DataReductionPrimary {
+                    window: DataReductionWindow {
+                        count: self.window,
+                        restart_tokens: self.restart_tokens.map(|v| vec![v]),
+                    },
                 },
-                cache_options: None,
-                query_id: "",
-                application_context: Some(ApplicationContext {
-                    dataset_id: "523ab509-8e2d-43ed-bfad-11fcd05180d7".into(),
-                    sources: vec![
-                        Source {
-                            report_id: "f508555a-b39d-4c10-8d46-a14bc282e079".into(),
-                        }
-                    ],
-                }),
-            }
-        ],
-        cancel_queries: vec![],
-        model_id: db.get_model_id(),
+            },
+            version: 1,
+        };
+
+        Request {
+            version: "1.0.0",
+            queries: vec![
+                RequestQueryWrapper {
+                    query: RequestQuery {
+                        commands: vec![
+                            RequestCommand {
+                                semantic_query_data_shape_command: QueryWrapper { query, binding },
+                            },
+                        ],
+                    },
+                    cache_options: self.cache_options,
+                    query_id: "",
+                    application_context: Some(ApplicationContext {
+                        dataset_id: report.dataset_id.clone(),
+                        sources: vec![
+                            Source {
+                                report_id: report.report_id.clone(),
+                            }
+                        ],
+                    }),
+                }
+            ],
+            cancel_queries: vec![],
+            model_id: report.model_id,
+        }
+    }
+
+    fn column(&self, property: &str) -> SelectColumn {
+        SelectColumn::new(&self.source, property)
+    }
+}
+
+/// Which officers to list from the index.  Each non-empty field narrows the
+/// list further; within a field, any of the values match.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexFilter {
+    pub commands: Vec<String>,
+    pub ranks: Vec<String>,
+    /// Only officers whose last name starts with this.
+    pub last_name_prefix: Option<String>,
+    pub shield_numbers: Vec<String>,
+}
+
+impl IndexFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == IndexFilter::default()
+    }
+
+    /// The filter as query conditions.
+    pub fn conditions(&self) -> Vec<Condition> {
+        let mut conditions = vec![];
+
+        if !self.commands.is_empty() {
+            conditions.push(Condition::is_in("Command", &self.commands));
+        }
+        if !self.ranks.is_empty() {
+            conditions.push(Condition::is_in("Rank", &self.ranks));
+        }
+        if let Some(prefix) = &self.last_name_prefix {
+            conditions.push(Condition::starts_with("Last Name", prefix));
+        }
+        if !self.shield_numbers.is_empty() {
+            conditions.push(Condition::is_in("Shield No", &self.shield_numbers));
+        }
+
+        conditions
+    }
+}
+
+pub fn get_index(report: &Report, filter: &IndexFilter, restart_tokens: Option<Vec<String>>, window: usize) -> Request {
+    let query = filter.conditions()
+        .into_iter()
+        .fold(Query::from("q1", report.entity.as_str()), Query::filter);
+
+    query
+        .select_as("Unique Id", officer_columns::ID)
+        .select_as("Command", officer_columns::COMMAND)
+        .select_as("Last Name", officer_columns::LAST_NAME)
+        .select_as("First Name", officer_columns::FIRST_NAME)
+        .select_as("Rank", officer_columns::RANK)
+        .select_as("Shield No", officer_columns::SHIELD_NO)
+        .order_by("Command", Direction::Ascending)
+        .window(window)
+        .restart_tokens(restart_tokens)
+        .cache_options(7)
+        .request(report)
+}
+
+/// Which of an officer's allegations to fetch.  Each field narrows the
+/// allegations further; within a field, any of the values match.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetailsFilter {
+    /// Only allegations of incidents on or after this date.
+    pub since: Option<chrono::NaiveDate>,
+    /// Only allegations of incidents on or before this date.
+    pub until: Option<chrono::NaiveDate>,
+    pub fado_types: Vec<model::FadoType>,
+    /// `Substantiated` without a recommendation matches every
+    /// substantiated allegation, whatever the board recommended.
+    pub board_dispositions: Vec<model::BoardDisposition>,
+    pub nypd_dispositions: Vec<model::NypdDisposition>,
+}
+
+impl DetailsFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == DetailsFilter::default()
     }
+
+    /// The filter as query conditions.
+    pub fn conditions(&self) -> Vec<Condition> {
+        let mut conditions = vec![];
+
+        match (&self.since, &self.until) {
+            (Some(since), Some(until)) => conditions.push(Condition::between("Incident Date", since, until)),
+            (Some(since), None) => conditions.push(Condition::compare("Incident Date", ComparisonKind::GreaterThanOrEqual, since)),
+            (None, Some(until)) => conditions.push(Condition::compare("Incident Date", ComparisonKind::LessThanOrEqual, until)),
+            (None, None) => {}
+        }
+        if !self.fado_types.is_empty() {
+            conditions.push(Condition::is_in("FADO Type", &self.fado_types));
+        }
+        let board = self.board_dispositions
+            .iter()
+            .map(|disposition| match disposition {
+                model::BoardDisposition::Substantiated(None) => Condition::starts_with("Board Disposition", "Substantiated"),
+                disposition => Condition::equal("Board Disposition", disposition),
+            })
+            .reduce(Condition::or);
+        if let Some(board) = board {
+            conditions.push(board);
+        }
+        if !self.nypd_dispositions.is_empty() {
+            conditions.push(Condition::is_in("NYPD Disposition", &self.nypd_dispositions));
+        }
+
+        conditions
+    }
+}
+
+pub fn get_followup(report: &Report, officer: &model::Officer, filter: &DetailsFilter) -> Request {
+    let query = Query::from("q1", report.entity.as_str())
+        .select_as("Rn", details_columns::INDEX)
+        .select_as("Complaint ID", details_columns::COMPLAINT_ID)
+        .select_as("Incident Date", details_columns::INCIDENT_DATE)
+        .select_as("FADO Type", details_columns::FADO_TYPE)
+        .select_as("Allegation", details_columns::ALLEGATION)
+        .select_as("Board Disposition", details_columns::BOARD_DISPOSITION)
+        .select_as("NYPD Disposition", details_columns::NYPD_DISPOSITION)
+        .select_as("Penalty", details_columns::PENALTY)
+        .filter(!Condition::equal("Rn", &0))
+        .filter_in("Shield No", [&officer.shield_no])
+        .filter_in("Last Name", [&officer.last_name])
+        .filter_in("First Name", [&officer.first_name])
+        .filter_in("Unique Id", [&officer.id])
+        .filter_in("Command", [&officer.command])
+        .filter_in("Rank", [&officer.rank]);
+
+    filter.conditions()
+        .into_iter()
+        .fold(query, Query::filter)
+        .order_by("Rn", Direction::Ascending)
+        .window(500)
+        .request(report)
+}
+
+/// Count the allegations in each group of `group_by`, over the officers
+/// and allegations the filters allow.
+pub fn get_summary(
+    report: &Report,
+    group_by: &[model::SummaryGroup],
+    index_filter: &IndexFilter,
+    details_filter: &DetailsFilter,
+    restart_tokens: Option<Vec<String>>,
+    window: usize,
+) -> Request {
+    let query = group_by
+        .iter()
+        .fold(Query::from("q1", report.entity.as_str()), |query, group| query.select_as(group.property(), group.column()))
+        .aggregate_as(Aggregate::CountNonNull, "Complaint ID", summary_columns::ALLEGATIONS)
+        .filter(!Condition::equal("Rn", &0));
+
+    let query = index_filter.conditions()
+        .into_iter()
+        .chain(details_filter.conditions())
+        .fold(query, Query::filter);
+
+    group_by
+        .iter()
+        .fold(query, |query, group| query.order_by(group.property(), Direction::Ascending))
+        .window(window)
+        .restart_tokens(restart_tokens)
+        .request(report)
 }
 
 #[cfg(test)]
@@ -704,14 +950,14 @@ mod test {
 
     #[test]
     fn serialize_query() {
-        let req = get_index(&Database::Active, Some(vec![
+        let req = get_index(&Report::active(), &IndexFilter::default(), Some(vec![
             "'a'".into(),
             "'b'".into(),
             "'c'".into(),
             "'d'".into(),
             "'e'".into(),
             "'f'".into(),
-        ]));
+        ]), DEFAULT_WINDOW);
 
         const EXPECTED: &str = "{\"version\":\"1.0.0\",\"queries\":[{\"Query\":{\"Commands\":[{\"SemanticQueryDataShapeCommand\":{\"Query\":{\"Version\":2,\"From\":[{\"Name\":\"q1\",\"Entity\":\"CCRB Active - Oracle\",\"Type\":0}],\"Select\":[{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Unique Id\"},\"Name\":\"Query1.Unique Id\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Command\"},\"Name\":\"Query1.Command1\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Last Name\"},\"Name\":\"Query1.Last Name1\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"First Name\"},\"Name\":\"Query1.First Name1\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Rank\"},\"Name\":\"Query1.Rank1\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Shield No\"},\"Name\":\"Query1.ShieldNo\"}],\"OrderBy\":[{\"Direction\":1,\"Expression\":{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Command\"}}}]},\"Binding\":{\"Primary\":{\"Groupings\":[{\"Projections\":[0,1,2,3,4,5]}]},\"DataReduction\":{\"DataVolume\":3,\"Primary\":{\"Window\":{\"Count\":500,\"RestartTokens\":[[\"\'a\'\",\"\'b\'\",\"\'c\'\",\"\'d\'\",\"\'e\'\",\"\'f\'\"]]}}},\"Version\":1}}}]},\"CacheOptions\":7,\"QueryId\":\"\",\"ApplicationContext\":{\"DatasetId\":\"523ab509-8e2d-43ed-bfad-11fcd05180d7\",\"Sources\":[{\"ReportId\":\"f508555a-b39d-4c10-8d46-a14bc282e079\"}]}}],\"cancelQueries\":[],\"modelId\":404287}";
 
@@ -722,16 +968,9 @@ mod test {
 
     #[test]
     fn serialize_followup() {
-        let officer = model::Officer {
-            id: "12345".into(),
-            command: "001 PD".into(),
-            last_name: "Doe".into(),
-            first_name: "Jane".into(),
-            rank: "Police Officer".into(),
-            shield_no: "98765".into(),
-        };
+        let officer = model::Officer::example();
 
-        let req = get_followup(&Database::Active, &officer);
+        let req = get_followup(&Report::active(), &officer, &DetailsFilter::default());
 
         const EXPECTED: &str = "{\"version\":\"1.0.0\",\"queries\":[{\"Query\":{\"Commands\":[{\"SemanticQueryDataShapeCommand\":{\"Query\":{\"Version\":2,\"From\":[{\"Name\":\"q1\",\"Entity\":\"CCRB Active - Oracle\",\"Type\":0}],\"Select\":[{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Rn\"},\"Name\":\"Sum(Query1.Rn)\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Complaint ID\"},\"Name\":\"CountNonNull(Query1.Complaint Id)1\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Incident Date\"},\"Name\":\"Query1.Incident Date\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"FADO Type\"},\"Name\":\"Query1.FADO Type1\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Allegation\"},\"Name\":\"Query1.Allegation1\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Board Disposition\"},\"Name\":\"Query1.Board Disposition1\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"NYPD Disposition\"},\"Name\":\"Query1.NYPD Disposition\"},{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Penalty\"},\"Name\":\"Query1.PenaltyDesc1\"}],\"Where\":[{\"Condition\":{\"Not\":{\"Expression\":{\"Comparison\":{\"ComparisonKind\":0,\"Left\":{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Rn\"}},\"Right\":{\"Literal\":{\"Value\":\"0L\"}}}}}}},{\"Condition\":{\"In\":{\"Expressions\":[{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Shield No\"}}],\"Values\":[[{\"Literal\":{\"Value\":\"\'98765\'\"}}]]}}},{\"Condition\":{\"In\":{\"Expressions\":[{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Last Name\"}}],\"Values\":[[{\"Literal\":{\"Value\":\"\'Doe\'\"}}]]}}},{\"Condition\":{\"In\":{\"Expressions\":[{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"First Name\"}}],\"Values\":[[{\"Literal\":{\"Value\":\"\'Jane\'\"}}]]}}},{\"Condition\":{\"In\":{\"Expressions\":[{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Unique Id\"}}],\"Values\":[[{\"Literal\":{\"Value\":\"\'12345\'\"}}]]}}},{\"Condition\":{\"In\":{\"Expressions\":[{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Command\"}}],\"Values\":[[{\"Literal\":{\"Value\":\"\'001 PD\'\"}}]]}}},{\"Condition\":{\"In\":{\"Expressions\":[{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Rank\"}}],\"Values\":[[{\"Literal\":{\"Value\":\"\'Police Officer\'\"}}]]}}}],\"OrderBy\":[{\"Direction\":1,\"Expression\":{\"Column\":{\"Expression\":{\"SourceRef\":{\"Source\":\"q1\"}},\"Property\":\"Rn\"}}}]},\"Binding\":{\"Primary\":{\"Groupings\":[{\"Projections\":[0,1,2,3,4,5,6,7]}]},\"DataReduction\":{\"DataVolume\":3,\"Primary\":{\"Window\":{\"Count\":500}}},\"Version\":1}}}]},\"QueryId\":\"\",\"ApplicationContext\":{\"DatasetId\":\"523ab509-8e2d-43ed-bfad-11fcd05180d7\",\"Sources\":[{\"ReportId\":\"f508555a-b39d-4c10-8d46-a14bc282e079\"}]}}],\"cancelQueries\":[],\"modelId\":404287}";
 
@@ -739,4 +978,163 @@ mod test {
 
         assert_eq!(actual, EXPECTED);
     }
+
+    #[test]
+    fn build_query() {
+        let req = Query::from("o", "Officers")
+            .select("Command")
+            .filter_in("Shield No", ["1", "2"])
+            .request(&Report::active());
+
+        let json = serde_json::to_value(&req).expect("serialize");
+        let query = &json["queries"][0]["Query"]["Commands"][0]["SemanticQueryDataShapeCommand"]["Query"];
+
+        assert_eq!(query["From"][0]["Entity"], "Officers");
+        assert_eq!(query["Select"][0]["Name"], "o.Command");
+        assert_eq!(query["Where"][0]["Condition"]["In"]["Values"], serde_json::json!([[{"Literal":{"Value":"'1'"}}],[{"Literal":{"Value":"'2'"}}]]));
+        assert_eq!(query["OrderBy"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn typed_literals() {
+        assert_eq!(Literal::new("O'Brien").as_str(), "'O''Brien'");
+        assert_eq!(Literal::new(&String::from("001 PD")).as_str(), "'001 PD'");
+        assert_eq!(Literal::new(&123).as_str(), "123L");
+        assert_eq!(Literal::new(&1.5).as_str(), "1.5D");
+        assert_eq!(Literal::new(&true).as_str(), "true");
+        assert_eq!(Literal::new(&None::<i64>).as_str(), "null");
+        assert_eq!(Literal::null(), Literal::new(&None::<&str>));
+        assert_eq!(Literal::new(&chrono::NaiveDate::from_ymd_opt(2019, 1, 31).expect("date")).as_str(), "datetime'2019-01-31T00:00:00'");
+    }
+
+    #[test]
+    fn index_filter() {
+        let filter = IndexFilter {
+            commands: vec!["001 PCT".into(), "002 PCT".into()],
+            last_name_prefix: Some("Mc".into()),
+            ..IndexFilter::default()
+        };
+
+        let req = get_index(&Report::active(), &filter, Some(vec!["'001 PCT'".into()]), 10);
+
+        let json = serde_json::to_value(&req).expect("serialize");
+        let command = &json["queries"][0]["Query"]["Commands"][0]["SemanticQueryDataShapeCommand"];
+        let column = |property: &str| serde_json::json!({"Column":{"Expression":{"SourceRef":{"Source":"q1"}},"Property":property}});
+
+        assert_eq!(command["Query"]["Where"], serde_json::json!([
+            {"Condition":{"In":{"Expressions":[column("Command")],"Values":[[{"Literal":{"Value":"'001 PCT'"}}],[{"Literal":{"Value":"'002 PCT'"}}]]}}},
+            {"Condition":{"StartsWith":{"Left":column("Last Name"),"Right":{"Literal":{"Value":"'Mc'"}}}}},
+        ]));
+        assert_eq!(command["Binding"]["DataReduction"]["Primary"]["Window"], serde_json::json!({"Count":10,"RestartTokens":[["'001 PCT'"]]}));
+
+        assert!(IndexFilter::default().is_empty());
+        assert!(!filter.is_empty());
+    }
+
+    #[test]
+    fn details_filter() {
+        let filter = DetailsFilter {
+            since: chrono::NaiveDate::from_ymd_opt(2018, 1, 1),
+            fado_types: vec![model::FadoType::Force],
+            board_dispositions: vec![model::BoardDisposition::Substantiated(None), model::BoardDisposition::Exonerated],
+            ..DetailsFilter::default()
+        };
+
+        let column = |property: &str| serde_json::json!({"Column":{"Expression":{"SourceRef":{"Source":"q1"}},"Property":property}});
+        let literal = |value: &str| serde_json::json!({"Literal":{"Value":value}});
+
+        let officer = model::Officer::example();
+
+        let json = serde_json::to_value(get_followup(&Report::active(), &officer, &filter)).expect("serialize");
+        let conditions = &json["queries"][0]["Query"]["Commands"][0]["SemanticQueryDataShapeCommand"]["Query"]["Where"];
+
+        // After the officer's own seven conditions.
+        assert_eq!(conditions.as_array().map(Vec::len), Some(10));
+        assert_eq!(conditions[7], serde_json::json!({"Condition":{"Comparison":{"ComparisonKind":2,"Left":column("Incident Date"),"Right":literal("datetime'2018-01-01T00:00:00'")}}}));
+        assert_eq!(conditions[8], serde_json::json!({"Condition":{"In":{"Expressions":[column("FADO Type")],"Values":[[literal("'Force'")]]}}}));
+        assert_eq!(conditions[9], serde_json::json!({"Condition":{"Or":{
+            "Left":{"StartsWith":{"Left":column("Board Disposition"),"Right":literal("'Substantiated'")}},
+            "Right":{"Comparison":{"ComparisonKind":0,"Left":column("Board Disposition"),"Right":literal("'Exonerated'")}},
+        }}}));
+
+        assert!(DetailsFilter::default().conditions().is_empty());
+    }
+
+    #[test]
+    fn aggregate_query() {
+        let req = Query::from("q1", "CCRB Active - Oracle")
+            .group_by("Command")
+            .aggregate(Aggregate::CountNonNull, "Complaint ID")
+            .aggregate_as(Aggregate::Max, "Incident Date", "Latest")
+            .request(&Report::active());
+
+        let json = serde_json::to_value(&req).expect("serialize");
+        let command = &json["queries"][0]["Query"]["Commands"][0]["SemanticQueryDataShapeCommand"];
+        let column = |property: &str| serde_json::json!({"Column":{"Expression":{"SourceRef":{"Source":"q1"}},"Property":property}});
+
+        assert_eq!(command["Query"]["Select"], serde_json::json!([
+            {"Column":{"Expression":{"SourceRef":{"Source":"q1"}},"Property":"Command"},"Name":"q1.Command"},
+            {"Aggregation":{"Expression":column("Complaint ID"),"Function":5},"Name":"CountNonNull(q1.Complaint ID)"},
+            {"Aggregation":{"Expression":column("Incident Date"),"Function":4},"Name":"Latest"},
+        ]));
+        assert_eq!(command["Binding"]["Primary"]["Groupings"], serde_json::json!([{"Projections":[0,1,2]}]));
+    }
+
+    #[test]
+    fn summary_query() {
+        let group_by = [model::SummaryGroup::Command, model::SummaryGroup::FadoType];
+        let filter = DetailsFilter {
+            fado_types: vec![model::FadoType::Force],
+            ..DetailsFilter::default()
+        };
+
+        let req = get_summary(&Report::active(), &group_by, &IndexFilter::default(), &filter, None, DEFAULT_WINDOW);
+
+        let json = serde_json::to_value(&req).expect("serialize");
+        let query = &json["queries"][0]["Query"]["Commands"][0]["SemanticQueryDataShapeCommand"]["Query"];
+
+        let names = query["Select"].as_array().expect("select").iter().map(|select| select["Name"].clone()).collect::<Vec<_>>();
+        assert_eq!(names, vec![summary_columns::COMMAND, summary_columns::FADO_TYPE, summary_columns::ALLEGATIONS]);
+        // Every allegation has a complaint id, so this counts allegations
+        // rather than distinct values.
+        assert_eq!(query["Select"][2]["Aggregation"], serde_json::json!({
+            "Expression":{"Column":{"Expression":{"SourceRef":{"Source":"q1"}},"Property":"Complaint ID"}},
+            "Function":5,
+        }));
+        assert_eq!(query["Where"].as_array().map(Vec::len), Some(2));
+        assert_eq!(query["OrderBy"][1]["Expression"]["Column"]["Property"], "FADO Type");
+    }
+
+    #[test]
+    fn condition_tree() {
+        let since = chrono::NaiveDate::from_ymd_opt(2019, 1, 1).expect("date");
+        let until = chrono::NaiveDate::from_ymd_opt(2020, 12, 31).expect("date");
+
+        let req = Query::from("q1", "CCRB Active - Oracle")
+            .select("Complaint ID")
+            .filter(Condition::between("Incident Date", &since, &until).and(Condition::equal("FADO Type", "Force")))
+            .filter(Condition::starts_with("Last Name", "O'B").or(!Condition::is_null("Shield No")))
+            .filter(Condition::compare("Rn", ComparisonKind::GreaterThanOrEqual, &1).and(Condition::contains("Command", "PCT")))
+            .request(&Report::active());
+
+        let json = serde_json::to_value(&req).expect("serialize");
+        let query = &json["queries"][0]["Query"]["Commands"][0]["SemanticQueryDataShapeCommand"]["Query"];
+        let column = |property: &str| serde_json::json!({"Column":{"Expression":{"SourceRef":{"Source":"q1"}},"Property":property}});
+        let literal = |value: &str| serde_json::json!({"Literal":{"Value":value}});
+
+        assert_eq!(query["Where"], serde_json::json!([
+            {"Condition":{"And":{
+                "Left":{"Between":{"Expression":column("Incident Date"),"LowerBound":literal("datetime'2019-01-01T00:00:00'"),"UpperBound":literal("datetime'2020-12-31T00:00:00'")}},
+                "Right":{"Comparison":{"ComparisonKind":0,"Left":column("FADO Type"),"Right":literal("'Force'")}},
+            }}},
+            {"Condition":{"Or":{
+                "Left":{"StartsWith":{"Left":column("Last Name"),"Right":literal("'O''B'")}},
+                "Right":{"Not":{"Expression":{"Comparison":{"ComparisonKind":0,"Left":column("Shield No"),"Right":literal("null")}}}},
+            }}},
+            {"Condition":{"And":{
+                "Left":{"Comparison":{"ComparisonKind":2,"Left":column("Rn"),"Right":literal("1L")}},
+                "Right":{"Contains":{"Left":column("Command"),"Right":literal("'PCT'")}},
+            }}},
+        ]));
+    }
 }
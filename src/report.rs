@@ -0,0 +1,326 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::query::Database;
+
+/// Where a database is published, and the identifiers needed to query it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Report {
+    /// The table the queries select from.
+    pub entity: String,
+    pub dataset_id: String,
+    pub report_id: String,
+    pub model_id: usize,
+    /// The key of the public report, sent as `X-PowerBI-ResourceKey`.
+    pub resource_key: String,
+    /// The public report's page, sent as the `Referer`.
+    pub referer: String,
+}
+
+impl Report {
+    /// The published report of the active database.
+    pub fn active() -> Self {
+        Report {
+            entity: "CCRB Active - Oracle".into(),
+            dataset_id: "523ab509-8e2d-43ed-bfad-11fcd05180d7".into(),
+            report_id: "f508555a-b39d-4c10-8d46-a14bc282e079".into(),
+            model_id: 404287,
+            resource_key: "b2c8d2f2-3ad1-48dc-883c-d4163a6e2d8f".into(),
+            referer: "https://app.powerbigov.us/view?r=eyJrIjoiYjJjOGQyZjItM2FkMS00OGRjLTg4M2MtZDQxNjNhNmUyZDhmIiwidCI6IjczZDYxNzk5LWMyODQtNDAyMi04ZDQxLTU0Y2M0ZjE5MjllZiJ9".into(),
+        }
+    }
+
+    /// The published report of the inactive database.
+    ///
+    /// The service has so far accepted the active report's entity, dataset
+    /// and report ids for the inactive model as well, so those are shared.
+    pub fn inactive() -> Self {
+        Report {
+            model_id: 404284,
+            resource_key: "87914378-578f-4f43-b75e-8ddaeafbdda2".into(),
+            referer: "https://app.powerbigov.us/view?r=eyJrIjoiODc5MTQzNzgtNTc4Zi00ZjQzLWI3NWUtOGRkYWVhZmJkZGEyIiwidCI6IjczZDYxNzk5LWMyODQtNDAyMi04ZDQxLTU0Y2M0ZjE5MjllZiJ9".into(),
+            ..Report::active()
+        }
+    }
+}
+
+/// What a public report's `app.powerbigov.us/view?r=…` URL says about it.
+///
+/// The `r` parameter is base64-encoded JSON naming the report's resource
+/// key (`k`) and tenant (`t`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Embed {
+    pub url: String,
+    pub resource_key: String,
+    pub tenant_id: String,
+}
+
+#[derive(Deserialize)]
+struct EmbedParameter {
+    k: String,
+    t: String,
+}
+
+impl Embed {
+    /// Decode a public report URL.
+    pub fn parse(url: &str) -> Result<Self, EmbedError> {
+        use base64::Engine;
+
+        let engine = base64::engine::GeneralPurpose::new(
+            &base64::alphabet::STANDARD,
+            base64::engine::GeneralPurposeConfig::new()
+                .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent),
+        );
+
+        let parsed = url::Url::parse(url).map_err(EmbedError::Url)?;
+        let r = parsed.query_pairs()
+            .find(|(name, _)| name == "r")
+            .map(|(_, value)| value.into_owned())
+            .ok_or(EmbedError::MissingParameter)?;
+
+        let json = engine.decode(r.trim()).map_err(EmbedError::Base64)?;
+        let parameter: EmbedParameter = serde_json::from_slice(&json).map_err(EmbedError::Json)?;
+
+        Ok(Embed {
+            url: url.into(),
+            resource_key: parameter.k,
+            tenant_id: parameter.t,
+        })
+    }
+}
+
+impl Report {
+    /// Point the report at the one published at `embed`.
+    pub fn with_embed(mut self, embed: &Embed) -> Self {
+        self.resource_key = embed.resource_key.clone();
+        self.referer = embed.url.clone();
+        self
+    }
+}
+
+/// Why a public report URL couldn't be decoded.
+#[derive(Debug)]
+pub enum EmbedError {
+    Url(url::ParseError),
+    /// The URL has no `r` parameter.
+    MissingParameter,
+    Base64(base64::DecodeError),
+    Json(serde_json::Error),
+}
+
+impl std::fmt::Display for EmbedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EmbedError::Url(e) => write!(f, "invalid report URL: {}", e),
+            EmbedError::MissingParameter => write!(f, "report URL has no r= parameter"),
+            EmbedError::Base64(e) => write!(f, "report URL's r= parameter isn't base64: {}", e),
+            EmbedError::Json(e) => write!(f, "report URL's r= parameter isn't a resource key: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EmbedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EmbedError::Url(e) => Some(e),
+            EmbedError::MissingParameter => None,
+            EmbedError::Base64(e) => Some(e),
+            EmbedError::Json(e) => Some(e),
+        }
+    }
+}
+
+/// The report of each database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reports {
+    pub active: Report,
+    pub inactive: Report,
+}
+
+impl Reports {
+    /// Read report settings from a TOML file, or JSON if the file name ends
+    /// in `.json`.  Anything the file leaves out keeps its built-in default.
+    ///
+    /// ```toml
+    /// [inactive]
+    /// model_id = 404284
+    /// resource_key = "87914378-578f-4f43-b75e-8ddaeafbdda2"
+    /// ```
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Error::ReportConfig { path: path.into(), source: ConfigError::Io(e) })?;
+
+        let config: ReportsConfig = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text).map_err(ConfigError::Json)
+        } else {
+            toml::from_str(&text).map_err(ConfigError::Toml)
+        }.map_err(|source| Error::ReportConfig { path: path.into(), source })?;
+
+        let mut reports = Reports::default();
+        config.active.apply(&mut reports.active);
+        config.inactive.apply(&mut reports.inactive);
+
+        Ok(reports)
+    }
+
+    pub fn get(&self, database: Database) -> &Report {
+        match database {
+            Database::Active => &self.active,
+            Database::Inactive => &self.inactive,
+        }
+    }
+
+    pub fn get_mut(&mut self, database: Database) -> &mut Report {
+        match database {
+            Database::Active => &mut self.active,
+            Database::Inactive => &mut self.inactive,
+        }
+    }
+}
+
+impl Default for Reports {
+    fn default() -> Self {
+        Reports {
+            active: Report::active(),
+            inactive: Report::inactive(),
+        }
+    }
+}
+
+/// A report settings file, where every setting is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ReportsConfig {
+    active: ReportConfig,
+    inactive: ReportConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ReportConfig {
+    entity: Option<String>,
+    dataset_id: Option<String>,
+    report_id: Option<String>,
+    model_id: Option<usize>,
+    resource_key: Option<String>,
+    referer: Option<String>,
+}
+
+impl ReportConfig {
+    fn apply(self, report: &mut Report) {
+        if let Some(entity) = self.entity {
+            report.entity = entity;
+        }
+        if let Some(dataset_id) = self.dataset_id {
+            report.dataset_id = dataset_id;
+        }
+        if let Some(report_id) = self.report_id {
+            report.report_id = report_id;
+        }
+        if let Some(model_id) = self.model_id {
+            report.model_id = model_id;
+        }
+        if let Some(resource_key) = self.resource_key {
+            report.resource_key = resource_key;
+        }
+        if let Some(referer) = self.referer {
+            report.referer = referer;
+        }
+    }
+}
+
+/// Why a report settings file couldn't be read.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Json(e) => write!(f, "{}", e),
+            ConfigError::Io(e) => write!(f, "{}", e),
+            ConfigError::Toml(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Json(e) => Some(e),
+            ConfigError::Io(e) => Some(e),
+            ConfigError::Toml(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn load(name: &str, text: &str) -> Result<Reports, Error> {
+        let path = std::env::temp_dir().join(format!("ccrb-export-{}-{}", std::process::id(), name));
+
+        std::fs::write(&path, text).expect("write");
+        let reports = Reports::load(&path);
+        std::fs::remove_file(&path).expect("cleanup");
+
+        reports
+    }
+
+    #[test]
+    fn load_partial_config() {
+        let toml = load("reports.toml", "[inactive]\nmodel_id = 1\nresource_key = \"key\"\n").expect("toml");
+
+        assert_eq!(toml.active, Report::active());
+        assert_eq!(toml.inactive.model_id, 1);
+        assert_eq!(toml.inactive.resource_key, "key");
+        assert_eq!(toml.inactive.dataset_id, Report::inactive().dataset_id);
+
+        let json = load("reports.json", r#"{"active":{"entity":"CCRB Active"}}"#).expect("json");
+
+        assert_eq!(json.active.entity, "CCRB Active");
+        assert_eq!(json.inactive, Report::inactive());
+    }
+
+    #[test]
+    fn parse_embed() {
+        let embed = Embed::parse(&Report::active().referer).expect("embed");
+
+        assert_eq!(embed.resource_key, Report::active().resource_key);
+        assert_eq!(embed.tenant_id, "73d61799-c284-4022-8d41-54cc4f1929ef");
+        assert_eq!(Report::active().with_embed(&embed), Report::active());
+
+        // Padding may be percent-encoded or missing, depending on where the
+        // URL was copied from.
+        for url in &[
+            "https://app.powerbigov.us/view?r=eyJrIjoiYWIiLCJ0IjoiZGVmIn0%3D",
+            "https://app.powerbigov.us/view?r=eyJrIjoiYWIiLCJ0IjoiZGVmIn0&pageName=ReportSection",
+        ] {
+            let embed = Embed::parse(url).expect("embed");
+            assert_eq!((embed.resource_key.as_str(), embed.tenant_id.as_str()), ("ab", "def"));
+        }
+
+        assert!(matches!(Embed::parse("https://app.powerbigov.us/view"), Err(EmbedError::MissingParameter)));
+        assert!(matches!(Embed::parse("https://app.powerbigov.us/view?r=bm90IGpzb24%3D"), Err(EmbedError::Json(_))));
+    }
+
+    #[test]
+    fn load_bad_config() {
+        let err = load("typo.toml", "[active]\nresourse_key = \"key\"\n").expect_err("unknown field");
+
+        assert!(matches!(err, Error::ReportConfig { source: ConfigError::Toml(_), .. }));
+
+        let missing = std::env::temp_dir().join(format!("ccrb-export-{}-missing.toml", std::process::id()));
+        let err = Reports::load(&missing).expect_err("missing file");
+
+        assert!(matches!(err, Error::ReportConfig { source: ConfigError::Io(_), .. }));
+        assert!(err.to_string().contains("missing.toml"));
+    }
+}
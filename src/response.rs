@@ -5,124 +5,376 @@ use serde::Deserialize;
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="camelCase")]
 pub struct Response {
-    job_ids: Vec<String>,
-    results: Vec<ResponseResult>,
+    #[serde(default)]
+    pub job_ids: Vec<String>,
+    #[serde(default)]
+    pub results: Vec<ResponseResult>,
+    pub error: Option<ServiceError>,
 }
 
 impl Response {
-    pub fn get_data(&self) -> Vec<Vec<String>> {
-        map_response(&self.results[0].result.data.dsr.d_s[0])
+    /// The error PowerBI reported instead of data, if any.
+    pub fn get_service_error(&self) -> Option<&ServiceError> {
+        self.error
+            .as_ref()
+            .or_else(|| self.results.iter().find_map(|r| r.result.error.as_ref()))
+    }
+
+    pub fn get_data(&self) -> std::result::Result<Vec<Vec<String>>, DecodeError> {
+        map_response(self.data_set()?)
+    }
+
+    /// The names of the columns in `get_data`'s rows, as given in the
+    /// query's select list.
+    pub fn get_columns(&self) -> std::result::Result<Vec<String>, DecodeError> {
+        let data = self.result_data()?;
+
+        let schema = match self.data_set()?.p_h.first().and_then(|ph| ph.d_m0.first()) {
+            Some(Dm0::A(start)) => &start.s,
+            _ => return Ok(vec![]),
+        };
+
+        schema
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                data.descriptor.select
+                    .iter()
+                    .find(|select| select.value == s.n)
+                    .map(|select| select.name.clone())
+                    .ok_or_else(|| DecodeError::at(0, Some(i), DecodeErrorKind::UnnamedColumn(s.n.clone())))
+            })
+            .collect()
     }
 
     pub fn get_restart_tokens(&self) -> Option<Vec<String>> {
-        self.results[0].result.data.dsr.d_s[0].r_t.as_ref().map(|v| v[0].clone())
+        self.data_set()
+            .ok()
+            .and_then(|ds| ds.r_t.as_ref())
+            .and_then(|rt| rt.first())
+            .cloned()
     }
+
+    fn result_data(&self) -> std::result::Result<&ResultData, DecodeError> {
+        self.results
+            .first()
+            .ok_or_else(|| DecodeError::new(DecodeErrorKind::NoResults))?
+            .result.data
+            .as_ref()
+            .ok_or_else(|| DecodeError::new(DecodeErrorKind::NoData))
+    }
+
+    fn data_set(&self) -> std::result::Result<&Ds, DecodeError> {
+        self.result_data()?
+            .dsr.d_s
+            .first()
+            .ok_or_else(|| DecodeError::new(DecodeErrorKind::NoDataSet))
+    }
+}
+
+/// A public report's metadata, from its `modelsAndExploration` endpoint.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+pub struct ModelsAndExploration {
+    #[serde(default)]
+    pub models: Vec<Model>,
+    pub exploration: Option<Exploration>,
+}
+
+impl ModelsAndExploration {
+    /// The model the report is built on, if the report names one of the
+    /// listed models.
+    pub fn model(&self) -> Option<&Model> {
+        let model_id = self.report()?.model_id?;
+
+        self.models
+            .iter()
+            .find(|model| model.id == model_id)
+    }
+
+    pub fn report(&self) -> Option<&ExplorationReport> {
+        self.exploration.as_ref().and_then(|e| e.report.as_ref())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+pub struct Model {
+    pub id: usize,
+    /// The dataset id.
+    pub db_name: String,
+    pub display_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+pub struct Exploration {
+    pub report: Option<ExplorationReport>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+pub struct ExplorationReport {
+    /// The report id.
+    pub object_id: String,
+    pub model_id: Option<usize>,
+}
+
+/// The conceptual schemas of some models.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+pub struct ConceptualSchemaResponse {
+    #[serde(default)]
+    pub schemas: Vec<ModelSchema>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+pub struct ModelSchema {
+    pub model_id: Option<usize>,
+    pub schema: ConceptualSchema,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="PascalCase")]
+pub struct ConceptualSchema {
+    #[serde(default)]
+    pub entities: Vec<SchemaEntity>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="PascalCase")]
+pub struct SchemaEntity {
+    pub name: String,
+    #[serde(default)]
+    pub properties: Vec<SchemaProperty>,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="PascalCase")]
+pub struct SchemaProperty {
+    pub name: String,
+    pub column: Option<SchemaType>,
+    pub measure: Option<SchemaType>,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="PascalCase")]
+pub struct SchemaType {
+    pub data_type: Option<usize>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="camelCase")]
 pub struct ResponseResult {
-    job_id: String,
-    result: Result,
+    pub job_id: String,
+    pub result: Result,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="camelCase")]
 pub struct Result {
-    data: ResultData,
+    pub data: Option<ResultData>,
+    pub error: Option<ServiceError>,
+}
+
+/// An error reported by the PowerBI service in place of a result.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ServiceError {
+    pub code: String,
+    #[serde(rename="pbi.error")]
+    pub pbi_error: Option<PbiError>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all="camelCase")]
+pub struct PbiError {
+    pub code: String,
+    #[serde(default)]
+    pub details: Vec<PbiErrorDetail>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all="camelCase")]
+pub struct PbiErrorDetail {
+    pub code: String,
+    pub detail: Option<PbiErrorValue>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all="camelCase")]
+pub struct PbiErrorValue {
+    #[serde(rename="type")]
+    pub value_type: usize,
+    pub value: String,
+}
+
+/// Broadly, what a `ServiceError` means for the exporter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceErrorKind {
+    /// The resource key, model or report is wrong or no longer published.
+    ResourceKey,
+    /// The query doesn't fit the model, e.g. after the report changed.
+    InvalidQuery,
+    /// The service is shedding load; try again later.
+    Throttled,
+    Other,
+}
+
+impl ServiceError {
+    /// The most specific error code given.
+    pub fn code(&self) -> &str {
+        self.pbi_error
+            .as_ref()
+            .map(|e| e.code.as_str())
+            .unwrap_or(&self.code)
+    }
+
+    /// A human-readable description, built from the error details.
+    pub fn message(&self) -> String {
+        let details = self.pbi_error
+            .iter()
+            .flat_map(|e| e.details.iter())
+            .map(|d| match &d.detail {
+                Some(detail) => format!("{}: {}", d.code, detail.value),
+                None => d.code.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        if details.is_empty() {
+            self.code.clone()
+        } else {
+            details.join("; ")
+        }
+    }
+
+    /// Classify the error by its codes.
+    pub fn kind(&self) -> ServiceErrorKind {
+        let codes = [self.code.as_str(), self.code()];
+        let any = |needles: &[&str]| codes.iter().any(|code| needles.iter().any(|n| code.contains(n)));
+
+        if any(&["Throttl", "TooManyRequests", "CapacityLimit", "Overloaded"]) {
+            ServiceErrorKind::Throttled
+        }
+        else if any(&["NotFound", "NotAuthorized", "Unauthorized", "ResourceKey", "TokenExpired"]) {
+            ServiceErrorKind::ResourceKey
+        }
+        else if any(&["Query", "DataShape", "Dsr", "Semantic"]) {
+            ServiceErrorKind::InvalidQuery
+        }
+        else {
+            ServiceErrorKind::Other
+        }
+    }
+}
+
+impl std::fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "PowerBI error {}: {}", self.code(), self.message())
+    }
+}
+
+impl std::error::Error for ServiceError {
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="camelCase")]
 pub struct ResultData {
-    timestamp: String,
-    root_activity_id: String,
-    descriptor: Descriptor,
-    from_cache: bool,
-    dsr: Dsr,
+    pub timestamp: String,
+    pub root_activity_id: String,
+    pub descriptor: Descriptor,
+    pub from_cache: bool,
+    pub dsr: Dsr,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Descriptor {
-    select: Vec<Select>,
-    expressions: Expressions,
-    version: usize,
+    pub select: Vec<Select>,
+    pub expressions: Expressions,
+    pub version: usize,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Select {
-    kind: usize,
-    depth: usize,
-    value: String,
-    group_keys: Vec<GroupKey>,
-    name: String,
+    pub kind: usize,
+    /// Aggregates have no depth or group keys.
+    #[serde(default)]
+    pub depth: usize,
+    pub value: String,
+    #[serde(default)]
+    pub group_keys: Vec<GroupKey>,
+    pub name: String,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct GroupKey {
-    source: Source,
-    calc: String,
-    is_same_as_select: bool,
+    pub source: Source,
+    pub calc: String,
+    pub is_same_as_select: bool,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Source {
-    entity: String,
-    property: String,
+    pub entity: String,
+    pub property: String,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Expressions {
-    primary: Primary,
+    pub primary: Primary,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Primary {
-    groupings: Vec<Grouping>,
+    pub groupings: Vec<Grouping>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Grouping {
-    keys: Vec<Key>,
+    pub keys: Vec<Key>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Key {
-    source: Source,
-    select: usize,
+    pub source: Source,
+    pub select: usize,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Dsr {
-    version: usize,
-    minor_version: usize,
-    d_s: Vec<Ds>,
+    pub version: usize,
+    pub minor_version: usize,
+    pub d_s: Vec<Ds>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Ds {
-    n: String,
-    p_h: Vec<Ph>,
-    i_c: bool,
-    h_a_d: Option<bool>,
-    r_t: Option<Vec<Vec<String>>>,
-    value_dicts: Option<HashMap<String, Vec<String>>>,
+    pub n: String,
+    pub p_h: Vec<Ph>,
+    pub i_c: bool,
+    pub h_a_d: Option<bool>,
+    pub r_t: Option<Vec<Vec<String>>>,
+    pub value_dicts: Option<HashMap<String, Vec<String>>>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Ph {
-    d_m0: Vec<Dm0>,
+    pub d_m0: Vec<Dm0>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -135,27 +387,27 @@ pub enum Dm0 {
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Dm0A {
-    s: Vec<S>,
-    c: Vec<usize>,
+    pub s: Vec<S>,
+    pub c: Vec<usize>,
     #[serde(rename="Ø")]
-    zero: Option<usize>,
+    pub zero: Option<usize>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct S {
-    n: String,
-    t: usize,
-    d_n: Option<String>,
+    pub n: String,
+    pub t: usize,
+    pub d_n: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all="PascalCase")]
 pub struct Dm0B {
-    c: Vec<Value>,
-    r: Option<usize>,
+    pub c: Vec<Value>,
+    pub r: Option<usize>,
     #[serde(rename="Ø")]
-    zero: Option<usize>,
+    pub zero: Option<usize>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -165,6 +417,203 @@ pub enum Value {
     Id(usize),
 }
 
+/// Why the data in a response couldn't be decoded, and where.
+#[derive(Debug)]
+pub struct DecodeError {
+    /// The index of the offending row in the data set, if any.
+    pub row: Option<usize>,
+    /// The index of the offending column, if any.
+    pub column: Option<usize>,
+    pub kind: DecodeErrorKind,
+}
+
+#[derive(Debug)]
+pub enum DecodeErrorKind {
+    NoResults,
+    NoData,
+    NoDataSet,
+    NoPrimaryHierarchy,
+    /// The first row didn't describe the columns.
+    MissingSchema,
+    /// A later row described the columns again.
+    UnexpectedSchema,
+    TooManyColumns(usize),
+    /// No column in the descriptor's select list has this value.
+    UnnamedColumn(String),
+    /// A text column didn't name its value dictionary.
+    MissingDictName,
+    UnknownDict(String),
+    DictIndexOutOfRange(usize),
+    InvalidDate(usize),
+    /// A row had fewer values than its columns called for.
+    MissingValue,
+}
+
+impl DecodeError {
+    fn new(kind: DecodeErrorKind) -> Self {
+        DecodeError { row: None, column: None, kind }
+    }
+
+    fn at(row: usize, column: Option<usize>, kind: DecodeErrorKind) -> Self {
+        DecodeError { row: Some(row), column, kind }
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(row) = self.row {
+            write!(f, "row {}: ", row)?;
+        }
+
+        if let Some(column) = self.column {
+            write!(f, "column {}: ", column)?;
+        }
+
+        match &self.kind {
+            DecodeErrorKind::NoResults => write!(f, "no results in response"),
+            DecodeErrorKind::NoData => write!(f, "no data in result"),
+            DecodeErrorKind::NoDataSet => write!(f, "no data set in result"),
+            DecodeErrorKind::NoPrimaryHierarchy => write!(f, "no primary hierarchy in data set"),
+            DecodeErrorKind::MissingSchema => write!(f, "first row doesn't describe the columns"),
+            DecodeErrorKind::UnexpectedSchema => write!(f, "expected a differential row"),
+            DecodeErrorKind::TooManyColumns(n) => write!(f, "too many columns ({})", n),
+            DecodeErrorKind::UnnamedColumn(value) => write!(f, "column {:?} isn't in the select list", value),
+            DecodeErrorKind::MissingDictName => write!(f, "no value dictionary named"),
+            DecodeErrorKind::UnknownDict(name) => write!(f, "unknown value dictionary {:?}", name),
+            DecodeErrorKind::DictIndexOutOfRange(i) => write!(f, "value dictionary index {} out of range", i),
+            DecodeErrorKind::InvalidDate(millis) => write!(f, "invalid date {}", millis),
+            DecodeErrorKind::MissingValue => write!(f, "not enough values"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+}
+
+/// How to turn the raw values of a column into strings.
+enum Column<'a> {
+    Number,
+    Date,
+    Dict(&'a [String]),
+}
+
+impl<'a> Column<'a> {
+    fn new(s: &S, value_dicts: Option<&'a HashMap<String, Vec<String>>>) -> std::result::Result<Self, DecodeErrorKind> {
+        match s.t {
+            // Doubles, and integers such as counts.
+            3 | 4 => Ok(Column::Number),
+            7 => Ok(Column::Date),
+            _ => {
+                let name = s.d_n.as_ref().ok_or(DecodeErrorKind::MissingDictName)?;
+                value_dicts
+                    .and_then(|dicts| dicts.get(name))
+                    .map(|dict| Column::Dict(dict))
+                    .ok_or_else(|| DecodeErrorKind::UnknownDict(name.clone()))
+            }
+        }
+    }
+
+    fn decode(&self, value: &Value) -> std::result::Result<String, DecodeErrorKind> {
+        let c = match value {
+            Value::Str(s) => return Ok(s.clone()),
+            Value::Id(c) => *c,
+        };
+
+        match self {
+            Column::Number => Ok(c.to_string()),
+            Column::Date => {
+                let millis = c;
+                let seconds = millis / 1000;
+                chrono::NaiveDateTime::from_timestamp_opt(seconds as _, 0)
+                    .map(|dt| dt.date().format("%Y-%m-%d").to_string())
+                    .ok_or(DecodeErrorKind::InvalidDate(millis))
+            }
+            Column::Dict(dict) => dict
+                .get(c)
+                .cloned()
+                .ok_or(DecodeErrorKind::DictIndexOutOfRange(c)),
+        }
+    }
+}
+
+fn map_response(data: &Ds) -> std::result::Result<Vec<Vec<String>>, DecodeError> {
+    let mut result = vec![];
+
+    let mut rows = data.p_h
+        .first()
+        .ok_or_else(|| DecodeError::new(DecodeErrorKind::NoPrimaryHierarchy))?
+        .d_m0
+        .iter()
+        .enumerate();
+
+    let start = match rows.next() {
+        None => return Ok(result),
+        Some((_, Dm0::A(start))) => start,
+        Some((_, Dm0::B(_))) => return Err(DecodeError::at(0, None, DecodeErrorKind::MissingSchema)),
+    };
+
+    if start.s.len() > usize::BITS as usize {
+        return Err(DecodeError::at(0, None, DecodeErrorKind::TooManyColumns(start.s.len())));
+    }
+
+    let columns = start.s
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            Column::new(s, data.value_dicts.as_ref())
+                .map_err(|kind| DecodeError::at(0, Some(i), kind))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let first = start.c.iter().map(|c| Value::Id(*c)).collect::<Vec<_>>();
+
+    let mut prev = vec![String::new(); columns.len()];
+    decode_row(&columns, &mut prev, 0, &first, 0, start.zero.unwrap_or_default())?;
+
+    result.push(prev.clone());
+
+    for (n, row) in rows {
+        match row {
+            Dm0::B(next) => {
+                let repeat = next.r.unwrap_or_default();
+                let zero = next.zero.unwrap_or_default();
+
+                decode_row(&columns, &mut prev, n, &next.c, repeat, zero)?;
+            }
+            Dm0::A(_) => {
+                return Err(DecodeError::at(n, None, DecodeErrorKind::UnexpectedSchema));
+            }
+        }
+
+        result.push(prev.clone());
+    }
+
+    Ok(result)
+}
+
+/// Update `row` in place from the values of row `n`.  Columns with their
+/// bit set in `repeat` keep their previous value, and those with their
+/// bit set in `zero` are empty.
+fn decode_row(columns: &[Column], row: &mut [String], n: usize, values: &[Value], repeat: usize, zero: usize) -> std::result::Result<(), DecodeError> {
+    let mut values = values.iter();
+
+    for (i, column) in columns.iter().enumerate() {
+        let bit = 1 << i;
+
+        if zero & bit != 0 {
+            row[i] = "".into();
+        }
+        else if repeat & bit == 0 {
+            let value = values.next()
+                .ok_or_else(|| DecodeError::at(n, Some(i), DecodeErrorKind::MissingValue))?;
+            row[i] = column.decode(value)
+                .map_err(|kind| DecodeError::at(n, Some(i), kind))?;
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -178,7 +627,7 @@ mod test {
 
         let model: Response = serde_json::from_str(RESPONSE).expect("deserialize");
 
-        assert_eq!(model.results[0].result.data.descriptor.select[0].group_keys[0].source.entity, "CCRB Active - Oracle");
+        assert_eq!(model.results[0].result.data.as_ref().expect("data").descriptor.select[0].group_keys[0].source.entity, "CCRB Active - Oracle");
     }
 
     #[test]
@@ -186,111 +635,100 @@ mod test {
 
         let model: Response = serde_json::from_str(DETAILS).expect("deserialize");
 
-        assert_eq!(model.results[0].result.data.descriptor.select[0].group_keys[0].source.entity, "CCRB Active - Oracle");
+        assert_eq!(model.results[0].result.data.as_ref().expect("data").descriptor.select[0].group_keys[0].source.entity, "CCRB Active - Oracle");
     }
-}
 
-fn map_response(data: &Ds) -> Vec<Vec<String>> {
-    let mut result = vec![];
+    #[test]
+    fn decode_details() {
+        let model: Response = serde_json::from_str(DETAILS).expect("deserialize");
 
-    let value_dicts = match &data.value_dicts {
-        None => return vec![],
-        Some(v) => v,
-    };
+        let rows = model.get_data().expect("decode");
 
-    let mut rows = data.p_h.get(0).expect("one row in PH").d_m0.iter();
-
-    let first = rows.next().expect("first row in DM0");
-
-    let (dicts, mut prev) = match first {
-        Dm0::A(start) => {
-            let dicts = start.s
-                .iter()
-                .map(|s| {
-                    match s.t {
-                        3 => {
-                            let b: Box<dyn Fn(usize) -> String> = Box::new(|c: usize| c.to_string());
-                            b
-                        }
-                        7 => {
-                            // TODO: format date
-                            let b: Box<dyn Fn(usize) -> String> = Box::new(|c: usize| {
-                                let millis = c;
-                                let seconds = millis / 1000;
-                                let dt = chrono::NaiveDateTime::from_timestamp(seconds as _, 0);
-                                dt.date().format("%Y-%m-%d").to_string()
-                            });
-                            b
-                        }
-                        _ => {
-                            let dict = value_dicts.get(s.d_n.as_ref().expect("value dict id")).expect("value dict");
-                            let b: Box<dyn Fn(usize) -> String> = Box::new(move |c| dict[c].clone());
-                            b
-                        }
-                    }
-                })
-                .collect::<Vec<Box<dyn Fn(usize) -> String>>>();
-
-            let mut row = vec![];
-
-            let mut items = start.c.iter();
-
-            for i in 0..dicts.len() {
-                let mask = start.zero.unwrap_or_default();
-                let bit = 1 << i;
-                if mask & bit == 0 {
-                    let item = items.next().expect("not enough items");
-                    row.push(dicts[i](*item));
-                }
-                else {
-                    row.push("".into());
-                }
-            }
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], vec!["1", "201803458", "2018-04-03", "Abuse of Authority", "Pepper spray", "Unsubstantiated", "", ""]);
+        assert_eq!(rows[3], vec!["4", "201803458", "2018-04-03", "Abuse of Authority", "Frisk", "Unsubstantiated", "", ""]);
+    }
 
-            (dicts, row)
-        }
-        _ => {
-            eprintln!("Expected first row to have full spec");
-            return vec![];
-        }
-    };
+    #[test]
+    fn decode_columns() {
+        let model: Response = serde_json::from_str(RESPONSE).expect("deserialize");
 
-    result.push(prev.clone());
+        assert_eq!(model.get_columns().expect("columns"), vec![
+            "Query1.Unique Id", "Query1.Command1", "Query1.Last Name1", "Query1.First Name1", "Query1.Rank1", "Query1.ShieldNo",
+        ]);
+    }
 
-    for row in rows {
-        let mut accum = prev.clone();
+    fn decode(ds: &str) -> std::result::Result<Vec<Vec<String>>, DecodeError> {
+        map_response(&serde_json::from_str(ds).expect("deserialize"))
+    }
 
-        match row {
-            Dm0::B(next) => {
-                let mask = next.r.unwrap_or_default();
-                let mut values = next.c.iter();
+    #[test]
+    fn decode_empty() {
+        let rows = decode(r#"{"N":"DS0","PH":[{"DM0":[]}],"IC":true}"#).expect("decode");
 
-                let zero = next.zero.unwrap_or_default();
+        assert!(rows.is_empty());
+    }
 
-                for i in 0..dicts.len() {
-                    let bit = 1 << i;
-
-                    if zero & bit != 0 {
-                        accum[i] = "".into();
-                    }
-                    else if mask & bit == 0 {
-                        let value = values.next().expect("not enough values");
-                        accum[i] = match value {
-                            Value::Str(s) => s.clone(),
-                            Value::Id(j) => dicts[i](*j),
-                        };
-                    }
-                }
-            }
-            _ => {
-                eprintln!("Expected subsequent row to have differential");
-                return result;
-            }
-        }
+    #[test]
+    fn decode_missing_schema() {
+        let err = decode(r#"{"N":"DS0","PH":[{"DM0":[{"C":[1]}]}],"IC":true}"#).expect_err("decode");
 
-        result.push(accum.clone());
-        prev = accum;
+        assert_eq!(err.row, Some(0));
+        assert!(matches!(err.kind, DecodeErrorKind::MissingSchema));
     }
 
-    return result;
+    #[test]
+    fn decode_missing_value_dicts() {
+        let err = decode(r#"{"N":"DS0","PH":[{"DM0":[{"S":[{"N":"G0","T":3},{"N":"G1","T":1,"DN":"D0"}],"C":[1,0]}]}],"IC":true}"#).expect_err("decode");
+
+        assert_eq!((err.row, err.column), (Some(0), Some(1)));
+        assert!(matches!(err.kind, DecodeErrorKind::UnknownDict(ref name) if name == "D0"));
+    }
+
+    #[test]
+    fn decode_bad_dict_index() {
+        let err = decode(r#"{"N":"DS0","PH":[{"DM0":[{"S":[{"N":"G0","T":1,"DN":"D0"}],"C":[0]},{"C":[5]}]}],"IC":true,"ValueDicts":{"D0":["a"]}}"#).expect_err("decode");
+
+        assert_eq!((err.row, err.column), (Some(1), Some(0)));
+        assert!(matches!(err.kind, DecodeErrorKind::DictIndexOutOfRange(5)));
+    }
+
+    #[test]
+    fn decode_counts() {
+        let rows = decode(r#"{"N":"DS0","PH":[{"DM0":[{"S":[{"N":"G0","T":1,"DN":"D0"},{"N":"M0","T":4}],"C":[0,12]},{"C":[1,3]}]}],"IC":true,"ValueDicts":{"D0":["001 PCT","005 PCT"]}}"#).expect("decode");
+
+        assert_eq!(rows, vec![vec!["001 PCT", "12"], vec!["005 PCT", "3"]]);
+    }
+
+    #[test]
+    fn decode_missing_value() {
+        let err = decode(r#"{"N":"DS0","PH":[{"DM0":[{"S":[{"N":"G0","T":3},{"N":"G1","T":3}],"C":[1,2]},{"C":[3]}]}],"IC":true}"#).expect_err("decode");
+
+        assert_eq!((err.row, err.column), (Some(1), Some(1)));
+        assert!(matches!(err.kind, DecodeErrorKind::MissingValue));
+    }
+
+    #[test]
+    fn deserialize_service_error() {
+        const ERROR: &str = r#"{"error":{"code":"PowerBIEntityNotFound","pbi.error":{"code":"PowerBIEntityNotFound","parameters":{},"details":[],"exceptionCulprit":1}}}"#;
+
+        let model: Response = serde_json::from_str(ERROR).expect("deserialize");
+        let error = model.get_service_error().expect("service error");
+
+        assert_eq!(error.code(), "PowerBIEntityNotFound");
+        assert_eq!(error.kind(), ServiceErrorKind::ResourceKey);
+        assert!(model.get_data().is_err());
+    }
+
+    #[test]
+    fn deserialize_result_error() {
+        const ERROR: &str = r#"{"jobIds":["b58ea924-0e62-44ac-87c9-f41461352146"],"results":[{"jobId":"b58ea924-0e62-44ac-87c9-f41461352146","result":{"error":{"code":"QueryExecutionError","pbi.error":{"code":"rsDataShapeQueryTranslationError","parameters":{},"details":[{"code":"DetailsMessage","detail":{"type":1,"value":"Invalid property 'Penalty'"}}]}}}}]}"#;
+
+        let model: Response = serde_json::from_str(ERROR).expect("deserialize");
+        let error = model.get_service_error().expect("service error");
+
+        assert_eq!(error.code(), "rsDataShapeQueryTranslationError");
+        assert_eq!(error.message(), "DetailsMessage: Invalid property 'Penalty'");
+        assert_eq!(error.kind(), ServiceErrorKind::InvalidQuery);
+    }
 }
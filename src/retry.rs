@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// How often, and how patiently, to retry a failed query.
+///
+/// The wait before the `n`th retry is `initial_backoff * multiplier^(n - 1)`,
+/// capped at `max_backoff`, with a random jitter of up to half of that
+/// subtracted so concurrent workers don't retry in lockstep.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The total number of attempts, including the first.
+    pub max_attempts: u32,
+    /// The wait before the first retry.
+    pub initial_backoff: Duration,
+    /// The longest wait between attempts.
+    pub max_backoff: Duration,
+    /// The factor the wait grows by after each retry.
+    pub multiplier: f64,
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// The wait before the given retry, without jitter.  A multiplier that
+    /// would make the wait negative (or NaN) makes it zero instead.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.powi(retry.saturating_sub(1) as i32);
+        let backoff = self.initial_backoff.as_secs_f64() * factor;
+
+        Duration::from_secs_f64(backoff.max(0.0).min(self.max_backoff.as_secs_f64()))
+    }
+
+    /// The wait before the given retry, with jitter.
+    pub fn jittered_backoff(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+
+        backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_schedule() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2.0,
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(800));
+        assert_eq!(policy.backoff(5), Duration::from_secs(1));
+        assert_eq!(policy.backoff(9), Duration::from_secs(1));
+
+        for retry in 1..10 {
+            let jittered = policy.jittered_backoff(retry);
+            assert!(jittered <= policy.backoff(retry));
+            assert!(jittered >= policy.backoff(retry) / 2);
+        }
+    }
+
+    #[test]
+    fn bad_multiplier() {
+        for multiplier in [-2.0, f64::NAN, f64::INFINITY] {
+            let policy = RetryPolicy {
+                multiplier,
+                ..RetryPolicy::default()
+            };
+
+            for retry in 1..5 {
+                assert!(policy.backoff(retry) <= policy.max_backoff);
+                assert!(policy.jittered_backoff(retry) <= policy.max_backoff);
+            }
+        }
+
+        let negative = RetryPolicy { multiplier: -2.0, ..RetryPolicy::default() };
+        assert_eq!(negative.backoff(2), Duration::ZERO);
+    }
+}
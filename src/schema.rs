@@ -0,0 +1,118 @@
+use serde::Serialize;
+
+use crate::response;
+
+/// A table of a report's model.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entity {
+    pub name: String,
+    pub hidden: bool,
+    pub properties: Vec<Property>,
+}
+
+/// A column or measure of an entity.
+#[derive(Debug, Clone, Serialize)]
+pub struct Property {
+    pub name: String,
+    pub kind: PropertyKind,
+    /// The name of the data type, or its code if it isn't one we know.
+    pub data_type: Option<String>,
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PropertyKind {
+    Column,
+    Measure,
+    Other,
+}
+
+impl PropertyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PropertyKind::Column => "column",
+            PropertyKind::Measure => "measure",
+            PropertyKind::Other => "other",
+        }
+    }
+}
+
+/// Name a conceptual schema data type.  The low byte is the primitive
+/// type, the same codes as a data shape's column types; the rest is a
+/// category such as numeric or temporal.
+fn data_type_name(code: usize) -> String {
+    let name = match code & 0xff {
+        1 => "Text",
+        2 => "Decimal",
+        3 => "Double",
+        4 => "Integer",
+        5 => "Boolean",
+        6 => "Date",
+        7 => "DateTime",
+        8 => "DateTimeZone",
+        9 => "Time",
+        10 => "Duration",
+        11 => "Binary",
+        13 => "Variant",
+        _ => return code.to_string(),
+    };
+
+    name.into()
+}
+
+impl From<&response::SchemaEntity> for Entity {
+    fn from(entity: &response::SchemaEntity) -> Self {
+        Entity {
+            name: entity.name.clone(),
+            hidden: entity.hidden,
+            properties: entity.properties.iter().map(Property::from).collect(),
+        }
+    }
+}
+
+impl From<&response::SchemaProperty> for Property {
+    fn from(property: &response::SchemaProperty) -> Self {
+        let (kind, data_type) = match (&property.column, &property.measure) {
+            (Some(column), _) => (PropertyKind::Column, column.data_type),
+            (None, Some(measure)) => (PropertyKind::Measure, measure.data_type),
+            (None, None) => (PropertyKind::Other, None),
+        };
+
+        Property {
+            name: property.name.clone(),
+            kind,
+            data_type: data_type.map(data_type_name),
+            hidden: property.hidden,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCHEMA: &str = r#"{"schemas":[{"schema":{"Version":2,"Entities":[{"Name":"CCRB Active - Oracle","Properties":[{"Name":"Command","Column":{"DataType":2049}},{"Name":"Rn","Column":{"DataType":260}},{"Name":"Incident Date","Column":{"DataType":519}},{"Name":"Allegation Count","Measure":{"DataType":259,"Aggregation":0}},{"Name":"Sort","Column":{"DataType":4096},"Hidden":true}]}]},"modelId":404287}]}"#;
+
+    #[test]
+    fn convert_schema() {
+        let resp: response::ConceptualSchemaResponse = serde_json::from_str(SCHEMA).expect("deserialize");
+        let entities = resp.schemas[0].schema.entities.iter().map(Entity::from).collect::<Vec<_>>();
+
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].name, "CCRB Active - Oracle");
+
+        let summary = entities[0].properties
+            .iter()
+            .map(|p| (p.name.as_str(), p.kind, p.data_type.as_deref().unwrap_or_default(), p.hidden))
+            .collect::<Vec<_>>();
+
+        assert_eq!(summary, vec![
+            ("Command", PropertyKind::Column, "Text", false),
+            ("Rn", PropertyKind::Column, "Integer", false),
+            ("Incident Date", PropertyKind::Column, "DateTime", false),
+            ("Allegation Count", PropertyKind::Measure, "Double", false),
+            ("Sort", PropertyKind::Column, "4096", true),
+        ]);
+    }
+}